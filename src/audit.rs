@@ -1,40 +1,382 @@
+use crate::config::Config;
+use crate::rules;
 use crate::scanner::Scanner;
-use crate::types::Vulnerability;
+use crate::types::{Location, Vulnerability};
+use proc_macro2::{TokenStream, TokenTree};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use syn::visit::Visit;
 use walkdir::WalkDir;
 
-pub fn scan_directory(path: &Path, crate_name: &str) -> Vec<Vulnerability> {
-    println!("🔍 Auditing for production panic patterns...");
-    
-    let mut scanner = Scanner::new(crate_name.to_string());
+/// Findings of a directory scan
+pub struct Scan {
+    pub findings: Vec<Vulnerability>,
+    /// Findings silenced by a `// panic-audit:allow` comment
+    pub suppressed: Vec<Vulnerability>,
+    /// Files that couldn't be read or parsed, so weren't audited
+    pub unparsed: Vec<ParseFailure>,
+}
+
+/// A `.rs` file left out of the scan, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParseFailure {
+    pub file: String,
+    pub error: String,
+}
+
+pub fn scan_directory(path: &Path, crate_name: &str, config: &Config) -> Scan {
+    eprintln!("🔍 Auditing for production panic patterns...");
+
+    let mut walker = WalkDir::new(path).follow_links(config.follow_symlinks);
+    if let Some(depth) = config.max_depth {
+        walker = walker.max_depth(depth);
+    }
 
-    let rs_files: Vec<_> = WalkDir::new(path)
+    let rs_files: Vec<_> = walker
         .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "rs"))
+        .filter_map(|e| {
+            // Symlink loops and unreadable directories are skipped, not fatal
+            e.map_err(|error| tracing::warn!(%error, "skipping directory entry")).ok()
+        })
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "rs"))
+        // Without --follow-symlinks a symlinked file is skipped like a symlinked directory
+        .filter(|e| config.follow_symlinks || !e.path_is_symlink())
         .collect();
 
-    println!("   Scanning {} Rust source files", rs_files.len());
-
-    for entry in rs_files {
-        scanner.current_file = entry
-            .path()
-            .strip_prefix(path)
-            .unwrap_or(entry.path())
-            .display()
-            .to_string();
-
-        if let Ok(content) = fs::read_to_string(entry.path()) {
-            // Store the source content for line number lookups
-            scanner.current_source = content.clone();
-            
-            if let Ok(syntax) = syn::parse_file(&content) {
-                scanner.visit_file(&syntax);
+    eprintln!("   Scanning {} Rust source files", rs_files.len());
+
+    // Each file gets its own scanner, so files are scanned in parallel and
+    // only the findings are merged; sorting makes the order deterministic
+    let parent = tracing::Span::current();
+    let per_file: Vec<(Vec<Vulnerability>, Vec<Vulnerability>, Option<ParseFailure>)> = rs_files
+        .par_iter()
+        .map(|entry| {
+            let mut scanner = Scanner::new(crate_name.to_string(), config.clone());
+            scanner.current_file = entry
+                .path()
+                .strip_prefix(path)
+                .unwrap_or(entry.path())
+                .display()
+                .to_string();
+
+            let _span =
+                tracing::info_span!(parent: &parent, "file", path = %scanner.current_file).entered();
+
+            let scanned = match fs::read_to_string(entry.path()) {
+                Ok(content) => scan_source(&mut scanner, content),
+                Err(e) => {
+                    tracing::warn!(error = %e, "skipping unreadable file");
+                    Err(e.to_string())
+                }
+            };
+            let failure = scanned
+                .err()
+                .map(|error| ParseFailure { file: scanner.current_file.clone(), error });
+            (scanner.vulnerabilities, scanner.suppressed, failure)
+        })
+        .collect();
+
+    let mut findings = Vec::new();
+    let mut suppressed = Vec::new();
+    let mut unparsed = Vec::new();
+    for (file_findings, file_suppressed, failure) in per_file {
+        findings.extend(file_findings);
+        suppressed.extend(file_suppressed);
+        unparsed.extend(failure);
+    }
+    sort_findings(&mut findings);
+    sort_findings(&mut suppressed);
+    unparsed.sort_by(|a, b| a.file.cmp(&b.file));
+    Scan { findings, suppressed, unparsed }
+}
+
+/// Order findings by `(file, line, rule_id)` so reports don't depend on
+/// directory walk order or thread scheduling. The sort is stable,
+/// so findings sharing a key keep their scan order.
+pub fn sort_findings(findings: &mut [Vulnerability]) {
+    findings.sort_by(|a, b| (&a.file, a.line, &a.rule_id).cmp(&(&b.file, b.line, &b.rule_id)));
+}
+
+/// Collapse findings with the same `(rule_id, code)` into the first one (in
+/// report order), which lists the rest in `also_at` and takes the worst
+/// severity among them.
+pub fn dedupe_across_files(findings: Vec<Vulnerability>) -> Vec<Vulnerability> {
+    let mut first_of: HashMap<(String, String), usize> = HashMap::new();
+    let mut deduped: Vec<Vulnerability> = Vec::new();
+
+    for vuln in findings {
+        let key = (vuln.rule_id.clone(), vuln.code.clone());
+        match first_of.get(&key) {
+            Some(&index) => {
+                let kept = &mut deduped[index];
+                if vuln.severity < kept.severity {
+                    kept.severity = vuln.severity;
+                }
+                kept.also_at.push(Location { file: vuln.file, line: vuln.line, column: vuln.column });
+            }
+            None => {
+                first_of.insert(key, deduped.len());
+                deduped.push(vuln);
+            }
+        }
+    }
+
+    deduped
+}
+
+/// Remove findings too thin to act on: whitespace-stripped code shorter than
+/// `min_code_length`, or (with `drop_unresolved`) a line that couldn't be
+/// located. Returns how many were dropped.
+pub fn drop_low_quality(findings: &mut Vec<Vulnerability>, min_code_length: usize, drop_unresolved: bool) -> usize {
+    let before = findings.len();
+    findings.retain(|vuln| {
+        let code_length = vuln.code.chars().filter(|c| !c.is_whitespace()).count();
+        code_length >= min_code_length && !(drop_unresolved && vuln.line_unresolved)
+    });
+    before - findings.len()
+}
+
+/// Deeper bracket nesting than any hand-written code; syn and the scanner
+/// recurse per level and would overflow the stack on adversarial input
+const MAX_NESTING: usize = 256;
+
+/// Stack for parsing and visiting one file. Only touched pages are committed,
+/// so this costs address space, not memory.
+const SCAN_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Scan one file's source as `scanner.current_file`. A file that can't be
+/// parsed is skipped with a warning; the error says why.
+pub fn scan_source(scanner: &mut Scanner, content: String) -> Result<(), String> {
+    if let Ok(tokens) = content.parse::<TokenStream>() {
+        let depth = nesting_depth(tokens);
+        if depth > MAX_NESTING {
+            tracing::warn!(depth, "skipping file nested too deeply to parse safely");
+            return Err(format!("nested {} levels deep (limit {})", depth, MAX_NESTING));
+        }
+    }
+
+    // Long chains (`a.b().c()...`, `x + x + ...`) nest the AST without
+    // brackets; parse and visit on a stack sized for generated code
+    let span = tracing::Span::current();
+    std::thread::scope(|scope| {
+        let scan = std::thread::Builder::new()
+            .stack_size(SCAN_STACK_SIZE)
+            .spawn_scoped(scope, move || {
+                let _span = span.entered();
+                match syn::parse_file(&content) {
+                    Ok(syntax) => {
+                        scanner.uses_sqlite = content.contains("sqlite");
+                        scanner.uses_tokio_sync = content.contains("tokio::sync");
+                        scanner.has_allow_comments = content.contains(rules::ALLOW_DIRECTIVE);
+                        // Store the source content for line number lookups
+                        scanner.current_source = content;
+                        scanner.visit_file(&syntax);
+                        Ok(())
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, "skipping unparseable file");
+                        let at = e.span().start();
+                        Err(format!("{}:{}: {}", at.line, at.column + 1, e))
+                    }
+                }
+            });
+        match scan {
+            // A panic in the scan thread is a scanner bug; keep it loud
+            Ok(handle) => handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+            Err(error) => {
+                tracing::warn!(%error, "skipping file: could not start scan thread");
+                Err(format!("could not start scan thread: {}", error))
+            }
+        }
+    })
+}
+
+/// Deepest `()`/`[]`/`{}` nesting, walked without recursion. Counted on
+/// tokens, so brackets inside strings and comments don't count.
+fn nesting_depth(tokens: TokenStream) -> usize {
+    let mut deepest = 0;
+    let mut open = vec![tokens.into_iter()];
+    while let Some(level) = open.last_mut() {
+        match level.next() {
+            Some(TokenTree::Group(group)) => {
+                open.push(group.stream().into_iter());
+                deepest = deepest.max(open.len() - 1);
+            }
+            Some(_) => {}
+            None => {
+                open.pop();
+            }
+        }
+    }
+    deepest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PanicClass, Severity};
+    use proptest::prelude::*;
+
+    fn finding(code: &str, line_unresolved: bool) -> Vulnerability {
+        Vulnerability {
+            line_unresolved,
+            ..Vulnerability::new(
+                "src/lib.rs".to_string(),
+                1,
+                "PA001",
+                Severity::Low,
+                PanicClass::AssumptionPanic,
+                "General Unwrap".to_string(),
+                code.to_string(),
+            )
+        }
+    }
+
+    #[test]
+    fn drops_short_code_and_optionally_unresolved_lines() {
+        let findings = || vec![finding("x . unwrap ()", false), finding("v[i]", false), finding("config . port . unwrap ()", true)];
+
+        let mut short = findings();
+        assert_eq!(drop_low_quality(&mut short, 11, false), 2);
+        assert_eq!(short[0].code, "config . port . unwrap ()");
+
+        let mut unresolved = findings();
+        assert_eq!(drop_low_quality(&mut unresolved, 0, true), 1);
+        assert!(unresolved.iter().all(|v| !v.line_unresolved));
+    }
+
+    const UNWRAP: &str = "pub fn load() -> String { std::fs::read_to_string(\"data.txt\").unwrap() }\n";
+
+    fn files_scanned(path: &Path, config: &Config) -> Vec<String> {
+        let mut files: Vec<_> = scan_directory(path, "fixture", config).findings.into_iter().map(|v| v.file).collect();
+        files.sort();
+        files
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_terminate() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("lib.rs"), UNWRAP).unwrap();
+        // src/again -> the crate root, src/alias.rs -> src/lib.rs
+        std::os::unix::fs::symlink(dir.path(), src.join("again")).unwrap();
+        std::os::unix::fs::symlink(src.join("lib.rs"), src.join("alias.rs")).unwrap();
+
+        assert_eq!(files_scanned(dir.path(), &Config::default()), ["src/lib.rs"]);
+
+        let follow = Config { follow_symlinks: true, ..Config::default() };
+        assert_eq!(files_scanned(dir.path(), &follow), ["src/alias.rs", "src/lib.rs"]);
+    }
+
+    /// Pieces of Rust, mostly valid on their own, that glue together into
+    /// near-miss source: unbalanced brackets, multibyte text, stray BOMs
+    const FRAGMENTS: &[&str] = &[
+        "pub fn f", "()", "(", ")", "{", "}", "[", "]", ";", "\n", "\r\n", "\u{feff}", " ", "let x = ", "v", "é",
+        "\"ünïcödé 🦀\"", "'", "r#\"", ".unwrap()", ".expect(\"e\")", "[i]", "panic!(\"x\")", "unreachable!()",
+        "std::fs::read_to_string(p)", "m.lock()", "#[test]", "#[cfg(test)] mod tests", "impl Drop for T",
+        "fn drop(&mut self)", "unsafe { }", "async", "// panic-audit:allow PA001", "/* ", " */", "::<u8>", "&",
+        "- ", "as usize", "Vec::with_capacity(n - k)", "loop", "for x in xs", "|x| x", "=>", "match a",
+    ];
+
+    fn rust_ish() -> impl Strategy<Value = String> {
+        prop::collection::vec(prop::sample::select(FRAGMENTS), 0..120).prop_map(|parts| parts.concat())
+    }
+
+    /// Scanning must only ever produce findings or a parse error
+    fn scan_any(source: String) -> Vec<Vulnerability> {
+        let config = Config { include_tests: true, ..Config::default() };
+        let mut scanner = Scanner::new("fuzz".to_string(), config);
+        scanner.current_file = "src/lib.rs".to_string();
+        let _ = scan_source(&mut scanner, source);
+        scanner.vulnerabilities
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_text_never_panics(source in any::<String>()) {
+            scan_any(source);
+        }
+
+        #[test]
+        fn rust_ish_source_never_panics(source in rust_ish()) {
+            let lines = source.lines().count().max(1);
+            for vuln in scan_any(source) {
+                prop_assert!(vuln.line >= 1 && vuln.line <= lines, "line {} of {}", vuln.line, lines);
             }
         }
+
+        #[test]
+        fn truncated_source_never_panics(cut in 0usize..400) {
+            let source = format!("{}\n// ünïcödé 🦀\n{}", UNWRAP, "pub fn g(v: &[u8]) -> u8 { v[0] + \"é\".len() as u8 }\n");
+            let end = source.char_indices().map(|(at, _)| at).nth(cut).unwrap_or(source.len());
+            scan_any(source[..end].to_string());
+        }
+    }
+
+    /// One file's worth of varied findings: the shape repeats every few files,
+    /// the names and line offsets don't
+    fn generated_file(n: usize) -> String {
+        let body = match n % 5 {
+            0 => format!("pub fn f{n}() -> String {{ std::fs::read_to_string(\"{n}.txt\").unwrap() }}"),
+            1 => format!("pub fn f{n}(v: &[u8], i: usize) -> u8 {{ v[i + {n}] }}"),
+            2 => format!("pub fn f{n}(m: &std::sync::Mutex<u8>) -> u8 {{ *m.lock().unwrap() }}"),
+            3 => format!("pub fn f{n}(s: &str) -> u32 {{ s.parse().expect(\"{n}\") }}"),
+            _ => format!("pub fn f{n}() {{ todo!() }}\nfn g{n}(x: Option<u8>) -> u8 {{ x.unwrap() }}"),
+        };
+        format!("{}{}\n", "\n".repeat(n % 7), body)
     }
 
-    scanner.vulnerabilities
+    #[test]
+    fn parallel_scan_matches_sequential_on_500_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for n in 0..500 {
+            let file = dir.path().join(format!("src/m{}/f{}.rs", n % 10, n));
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, generated_file(n)).unwrap();
+        }
+        let config = Config::default();
+
+        // Reference: one file at a time on this thread
+        let mut sequential = Vec::new();
+        for n in 0..500 {
+            let mut scanner = Scanner::new("fixture".to_string(), config.clone());
+            scanner.current_file = format!("src/m{}/f{}.rs", n % 10, n);
+            scan_source(&mut scanner, generated_file(n)).unwrap();
+            sequential.extend(scanner.vulnerabilities);
+        }
+        let key = |v: &Vulnerability| (v.file.clone(), v.line, v.column, v.rule_id.clone());
+        sequential.sort_by_key(key);
+
+        let on_threads = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| scan_directory(dir.path(), "fixture", &config).findings)
+        };
+        let parallel = on_threads(8);
+        assert_eq!(parallel.len(), sequential.len());
+        assert!(sequential.len() >= 500, "{} findings", sequential.len());
+
+        let mut unordered = parallel.clone();
+        unordered.sort_by_key(key);
+        assert_eq!(unordered, sequential);
+        // Not just the same set: the same report order for any thread count
+        assert_eq!(on_threads(1), parallel);
+    }
+
+    #[test]
+    fn max_depth_limits_the_walk() {
+        let dir = tempfile::tempdir().unwrap();
+        let deep = dir.path().join("src/a/b");
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), UNWRAP).unwrap();
+        std::fs::write(deep.join("c.rs"), UNWRAP).unwrap();
+
+        assert_eq!(files_scanned(dir.path(), &Config::default()), ["src/a/b/c.rs", "src/lib.rs"]);
+        let shallow = Config { max_depth: Some(2), ..Config::default() };
+        assert_eq!(files_scanned(dir.path(), &shallow), ["src/lib.rs"]);
+    }
 }
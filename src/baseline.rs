@@ -0,0 +1,134 @@
+use crate::diff::BaselineDelta;
+use crate::types::Vulnerability;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Accepted findings written by `--write-baseline`; later `--baseline` runs
+/// report only findings not in it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub entries: Vec<BaselineEntry>,
+}
+
+/// One accepted finding. Only `fingerprint` is matched; the rest is there so
+/// the file can be reviewed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    pub rule_id: String,
+    pub file: String,
+    pub code: String,
+}
+
+/// Findings left after matching against a baseline
+pub struct Filtered {
+    /// Not in the baseline
+    pub new: Vec<Vulnerability>,
+    /// Matched a baseline entry
+    pub known: usize,
+    /// Baseline entries nothing matched (fixed, or code changed)
+    pub stale: usize,
+}
+
+impl Baseline {
+    pub fn from_findings(findings: &[Vulnerability]) -> Self {
+        let entries = findings
+            .iter()
+            .map(|vuln| BaselineEntry {
+                fingerprint: vuln.id.clone(),
+                rule_id: vuln.rule_id.clone(),
+                file: vuln.file.clone(),
+                code: vuln.code.clone(),
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid baseline {}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n").with_context(|| format!("Failed to write baseline {}", path.display()))
+    }
+
+    /// Drop findings the baseline accepts. Like `diff::diff`, repeated
+    /// identical findings are matched one-for-one, so a second copy of an
+    /// accepted unwrap still counts as new.
+    pub fn filter(&self, findings: Vec<Vulnerability>) -> Filtered {
+        let mut remaining: HashMap<&str, usize> = HashMap::new();
+        for entry in &self.entries {
+            *remaining.entry(entry.fingerprint.as_str()).or_default() += 1;
+        }
+
+        let mut new = Vec::new();
+        let mut known = 0;
+        for vuln in findings {
+            match remaining.get_mut(vuln.id.as_str()) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    known += 1;
+                }
+                _ => new.push(vuln),
+            }
+        }
+
+        let stale = remaining.values().sum();
+        Filtered { new, known, stale }
+    }
+}
+
+impl Filtered {
+    /// New findings against stale entries, which are the ones resolved
+    pub fn delta(&self) -> BaselineDelta {
+        BaselineDelta::new(&self.new, self.stale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PanicClass, Severity};
+
+    fn finding(code: &str, severity: Severity) -> Vulnerability {
+        let file = "src/lib.rs".to_string();
+        Vulnerability::new(file, 1, "PA001", severity, PanicClass::AssumptionPanic, "General Unwrap".to_string(), code.to_string())
+    }
+
+    #[test]
+    fn delta_counts_introduced_and_resolved_findings() {
+        let old = [
+            finding("a.unwrap()", Severity::Low),
+            finding("b.unwrap()", Severity::High),
+            finding("c.unwrap()", Severity::Medium),
+            finding("d.unwrap()", Severity::Critical),
+        ];
+        let new = vec![
+            finding("a.unwrap()", Severity::Low),
+            finding("e.unwrap()", Severity::Critical),
+            finding("f.unwrap()", Severity::Low),
+        ];
+
+        let filtered = Baseline::from_findings(&old).filter(new);
+        assert_eq!((filtered.new.len(), filtered.known, filtered.stale), (2, 1, 3));
+
+        let delta = filtered.delta();
+        assert_eq!(
+            delta,
+            BaselineDelta { introduced: 2, introduced_critical: 1, introduced_high: 0, resolved: 3, net: -1 }
+        );
+        assert_eq!(delta.summary(), "Introduced 2 new (1 Critical), resolved 3, net -1");
+    }
+
+    #[test]
+    fn a_second_copy_of_an_accepted_finding_is_new() {
+        let accepted = Baseline::from_findings(&[finding("a.unwrap()", Severity::Low)]);
+        let filtered = accepted.filter(vec![finding("a.unwrap()", Severity::Low), finding("a.unwrap()", Severity::Low)]);
+        assert_eq!((filtered.new.len(), filtered.known, filtered.stale), (1, 1, 0));
+    }
+}
@@ -1,46 +1,338 @@
-use clap::Parser;
+use crate::download::DownloadOptions;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
-const VERSION: &str = "0.5.2";
+pub const VERSION: &str = "0.5.2";
 const TAGLINE: &str = "Find panic patterns that can take down production Rust services";
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Audit completed and no gate failed (always 0 with --no-exit)
+  1  A gate failed (--fail-on-findings with critical findings, --warnings-as-errors
+     with any finding, a --max-* budget was exceeded, or a config [[policy]] rule
+     failed) or the run errored
+  2  --strict-parse and a file could not be read or parsed
+
+With --exit-code-by-severity the exit code encodes the worst finding instead
+(10 Critical, 11 High, 12 Medium, 13 Low, 0 no findings); 1 still means the run
+errored. This replaces the gate's 1, but the gate is still reported.
+--strict-parse's 2 takes precedence over --exit-code-by-severity, and it
+conflicts with --no-exit.
+
+Gate precedence:
+  --no-exit            overrides every gate below (the run still reports would_fail)
+  --max-* budgets      when any is set, replace --fail-on-findings/--warnings-as-errors
+  --fail-on-findings   fails on Critical findings
+  --warnings-as-errors fails on any finding, whatever its severity
+  [[policy]] rules from the config are checked in addition to the flags";
 
 #[derive(Parser, Debug)]
 #[command(name = "cargo-panic-audit")]
 #[command(version = VERSION)]
 #[command(about = TAGLINE, long_about = None)]
+#[command(after_help = EXIT_CODES)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Crate name to audit (from crates.io) or local path to scan
-    pub crate_name: String,
+    #[arg(required_unless_present_any = ["legend", "config_print_effective", "deps"])]
+    pub crate_name: Option<String>,
 
     /// Specific version (defaults to latest) - ignored for local paths
+    #[arg(id = "crate_version", value_name = "VERSION")]
     pub version: Option<String>,
 
     /// Show all severity levels including low-risk patterns
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub verbose: bool,
 
     /// Show detailed explanations of each pattern class
     #[arg(short, long)]
     pub explain: bool,
 
-    /// Output JSON instead of human readable
-    #[arg(long)]
+    /// Output JSON instead of human readable (same as --format json)
+    #[arg(long, global = true)]
     pub json: bool,
 
-    /// Fail with non-zero exit code if critical findings exist
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human, global = true)]
+    pub format: OutputFormat,
+
+    /// When to use colors in human output
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    pub color: ColorChoice,
+
+    /// One editor diagnostic per line with exact spans (same as --format json-lines)
+    #[arg(long)]
+    pub list_findings_json_lines: bool,
+
+    /// SARIF 2.1.0 for GitHub code scanning (same as --format sarif)
+    #[arg(long, global = true)]
+    pub sarif: bool,
+
+    /// Fail with non-zero exit code if critical findings exist (ignored when a --max-* budget is set)
     #[arg(long)]
     pub fail_on_findings: bool,
 
+    /// Fail with non-zero exit code if any finding exists, whatever its severity
+    /// (ignored when a --max-* budget is set)
+    #[arg(long)]
+    pub warnings_as_errors: bool,
+
+    /// Fail if more than N critical findings exist
+    #[arg(long, value_name = "N")]
+    pub max_critical: Option<usize>,
+
+    /// Fail if more than N high findings exist
+    #[arg(long, value_name = "N")]
+    pub max_high: Option<usize>,
+
+    /// Fail if more than N medium findings exist
+    #[arg(long, value_name = "N")]
+    pub max_medium: Option<usize>,
+
+    /// Fail if more than N low findings exist
+    #[arg(long, value_name = "N")]
+    pub max_low: Option<usize>,
+
+    /// Always exit 0; machine formats still report `would_fail`
+    #[arg(long)]
+    pub no_exit: bool,
+
+    /// Exit with a code for the worst severity found (10 Critical, 11 High,
+    /// 12 Medium, 13 Low, 0 none) for routing in CI
+    #[arg(long, conflicts_with = "no_exit")]
+    pub exit_code_by_severity: bool,
+
+    /// Exit with code 2 if any .rs file couldn't be read or parsed, so an
+    /// unaudited file never passes as clean; takes precedence over other codes
+    #[arg(long, conflicts_with = "no_exit")]
+    pub strict_parse: bool,
+
+    /// Compare a local crate (--local) against its latest crates.io release and
+    /// report new/fixed findings; gates apply to the new findings only
+    #[arg(long)]
+    pub compare_with_crates_io: bool,
+
+    /// Scan again with another config (e.g. changed [severity] overrides) and
+    /// report the findings only one of the two rule configurations produces
+    #[arg(long, value_name = "CONFIG", conflicts_with = "compare_with_crates_io")]
+    pub compare_rules: Option<PathBuf>,
+
+    /// Also scan `cargo expand` output for macro-generated panics (local paths only;
+    /// needs cargo-expand and a nightly toolchain)
+    #[arg(long)]
+    pub audit_macros_expanded: bool,
+
+    /// Collapse findings with the same rule and code into one entry listing every
+    /// location (gates still count each location)
+    #[arg(long)]
+    pub dedupe_across_files: bool,
+
+    /// Also scan `#[test]` fns and `tests/` files, marking their findings `in_test`
+    #[arg(long)]
+    pub include_tests: bool,
+
+    /// Report unwraps and indexing in the statement right after an `unsafe { .. }`
+    /// statement as Low, trusting the invariant it established (heuristic)
+    #[arg(long)]
+    pub trust_unsafe_invariants: bool,
+
+    /// Only descend N directory levels below the scanned path
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Descend into symlinked directories (cycles are detected and skipped)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Derive each finding's severity from its context (untrusted input, hot path,
+    /// amplification, FFI abort) with the `[exploitability]` weights
+    #[arg(long)]
+    pub rule_severity_from_exploitability: bool,
+
+    /// Drop findings whose whitespace-stripped code is shorter than N characters
+    #[arg(long, value_name = "N")]
+    pub min_code_length: Option<usize>,
+
+    /// Drop findings whose snippet couldn't be located in the source (their line
+    /// is a placeholder)
+    #[arg(long)]
+    pub drop_unresolved: bool,
+
+    /// Scan files on N threads (default: one per CPU); output is the same for any N
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub threads: Option<usize>,
+
+    /// Report only findings not in this baseline file (matched by rule, file and
+    /// normalized code, not line)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["compare_with_crates_io", "compare_rules"])]
+    pub baseline: Option<PathBuf>,
+
+    /// Write the current findings to the --baseline file, accepting them
+    #[arg(long, requires = "baseline")]
+    pub write_baseline: bool,
+
+    /// Config file (defaults to .panic-audit.toml in the current directory, if present)
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Diagnostic log level or RUST_LOG-style filter (logs go to stderr; off by default)
+    #[arg(long, value_name = "LEVEL")]
+    pub log_level: Option<String>,
+
     /// Print rule legend and exit
     #[arg(long)]
     pub legend: bool,
 
-    /// Print summary only
+    /// Print the configuration in effect (config file merged with command-line
+    /// flags) as TOML, or JSON with --json, and exit
     #[arg(long)]
+    pub config_print_effective: bool,
+
+    /// Print summary only
+    #[arg(long, global = true)]
     pub summary: bool,
 
+    /// Show each Critical/High finding's source line with a caret at the
+    /// flagged expression (human output)
+    #[arg(long, global = true)]
+    pub pretty_context: bool,
+
     /// Scan local path instead of downloading from crates.io
     #[arg(short, long)]
     pub local: bool,
+
+    /// Directory holding the crate's Cargo.toml, when the scanned local path is
+    /// a subdirectory of it (crate name, `cargo expand`, crates.io comparison)
+    #[arg(long, value_name = "PATH", requires = "local")]
+    pub crate_root: Option<PathBuf>,
+
+    /// Download and scan every crates.io dependency in this Cargo.lock instead
+    /// of one crate; results are grouped by crate, riskiest first
+    #[arg(
+        long,
+        value_name = "CARGO_LOCK",
+        conflicts_with_all = ["local", "compare_with_crates_io", "compare_rules", "baseline", "audit_macros_expanded"]
+    )]
+    pub deps: Option<PathBuf>,
+
+    /// With --deps, only scan dependencies whose locked version crates.io
+    /// published at or after this time (RFC 3339, e.g. 2024-06-01T00:00:00Z)
+    #[arg(long, value_name = "RFC3339", requires = "deps", value_parser = crate::deps::parse_timestamp)]
+    pub since: Option<std::time::SystemTime>,
+
+    /// With --deps, stop at the first dependency whose own findings fail the
+    /// gate, instead of scanning the rest
+    #[arg(long, requires = "deps")]
+    pub fail_fast: bool,
+
+    /// With --deps, print only per-crate counts, score and grade as JSON,
+    /// without individual findings
+    #[arg(long, requires = "deps", conflicts_with_all = ["json", "format", "sarif", "list_findings_json_lines"])]
+    pub summary_json: bool,
+
+    /// With --deps, also write each crate's report in the --format to
+    /// <DIR>/<crate>-<version>.<ext>, plus an index.json rollup
+    #[arg(long, value_name = "DIR", requires = "deps")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Keep downloaded crates here and reuse them on later runs (default:
+    /// ~/.cache/cargo-panic-audit, or $XDG_CACHE_HOME/cargo-panic-audit)
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Download crates afresh into a temporary directory, bypassing the cache
+    #[arg(long, conflicts_with = "cache_dir")]
+    pub no_cache: bool,
+
+    /// Unpack downloads without checking them against the crates.io checksum
+    /// (offline mirrors); unverified downloads are not cached
+    #[arg(long)]
+    pub skip_checksum: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print a saved `--json` report with any output format, without rescanning
+    Render {
+        /// Report written by `--json`
+        report: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal (honors NO_COLOR / CLICOLOR_FORCE)
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored terminal report
+    Human,
+    /// Report object with all findings
+    Json,
+    /// JUnit XML, one test suite per panic class
+    Junit,
+    /// Code Climate issues for GitLab code quality
+    Codeclimate,
+    /// SonarQube generic issue import JSON
+    Sonarqube,
+    /// Editor diagnostics, one JSON object per line, grouped by file
+    JsonLines,
+    /// Markdown job summary, appended to $GITHUB_STEP_SUMMARY (stdout if unset)
+    GithubStepSummary,
+    /// Generic findings XML for XSLT-based tooling
+    Xml,
+    /// SARIF 2.1.0 log for GitHub code scanning
+    Sarif,
+}
+
+impl OutputFormat {
+    /// File extension for a report written in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Human => "txt",
+            OutputFormat::Json | OutputFormat::Codeclimate | OutputFormat::Sonarqube => "json",
+            OutputFormat::JsonLines => "jsonl",
+            OutputFormat::GithubStepSummary => "md",
+            OutputFormat::Junit | OutputFormat::Xml => "xml",
+            OutputFormat::Sarif => "sarif",
+        }
+    }
+}
+
+impl Args {
+    pub fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else if self.list_findings_json_lines {
+            OutputFormat::JsonLines
+        } else if self.sarif {
+            OutputFormat::Sarif
+        } else {
+            self.format
+        }
+    }
+
+    /// Download cache (unless --no-cache) and checksum check (unless --skip-checksum)
+    pub fn download_options(&self) -> DownloadOptions {
+        let cache = if self.no_cache {
+            None
+        } else {
+            self.cache_dir.clone().or_else(crate::download::default_cache_dir)
+        };
+        DownloadOptions { cache, verify_checksum: !self.skip_checksum }
+    }
+
+    /// Machine formats keep stdout free of banners and verdict text
+    pub fn is_machine_output(&self) -> bool {
+        self.output_format() != OutputFormat::Human || self.summary_json
+    }
 }
 
 pub fn parse() -> Args {
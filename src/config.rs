@@ -0,0 +1,235 @@
+use crate::rules;
+use crate::types::{PanicClass, Severity, Vulnerability};
+use anyhow::{Context, Result};
+use globset::{Glob, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Looked up in the current directory when `--config` is not given
+pub const DEFAULT_CONFIG_FILE: &str = ".panic-audit.toml";
+
+/// Settings from `.panic-audit.toml`. Every key is optional.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Function names whose unwraps are treated as benign and reported as
+    /// Low. Matched against the bare name of the enclosing `fn` (no module
+    /// or type path); `*` matches any run of characters.
+    pub trusted_functions: Vec<String>,
+
+    /// Extra parse/load calls (`mylib::parse_record`) reported like the
+    /// built-in image/csv/calamine ones when unwrapped
+    pub untrusted_parsers: Vec<String>,
+
+    /// `[[policy]]` gating rules; the first rule matching a finding decides
+    /// whether it fails the run
+    pub policy: Vec<PolicyRule>,
+
+    /// `[severity]` table of rule id to severity (`PA006 = "low"`), replacing
+    /// the severity of every finding of that rule
+    pub severity: BTreeMap<String, Severity>,
+
+    /// Scan `#[test]` fns and `tests/` files too, marking their findings
+    /// `in_test` (also set by `--include-tests`)
+    pub include_tests: bool,
+
+    /// Report unwraps and indexing in the statement right after an
+    /// `unsafe { .. }` statement in the same block as Low (also
+    /// `--trust-unsafe-invariants`); nested blocks, closures and loops aren't
+    /// covered.
+    /// Off by default: it is a heuristic, and hides real bugs when the unsafe
+    /// code doesn't actually establish what the next statement relies on.
+    pub trust_unsafe_invariants: bool,
+
+    /// Directory levels below the scan root to descend (also `--max-depth`);
+    /// unlimited when absent
+    pub max_depth: Option<usize>,
+
+    /// Descend into symlinked directories (also `--follow-symlinks`). Off by
+    /// default; when on, symlink cycles are detected and skipped.
+    pub follow_symlinks: bool,
+
+    /// `[exploitability]` weights for deriving severity from a finding's
+    /// context instead of its rule
+    pub exploitability: Exploitability,
+
+    /// File this config was loaded from
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+/// One `[[policy]]` entry. A rule matches a finding when every selector it
+/// sets matches; a rule with no selectors matches everything.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Panic class as reported in JSON output (e.g. `PanicAmplification`)
+    pub class: Option<PanicClass>,
+    /// Glob over the finding's path relative to the crate root (`src/net/**`)
+    pub path: Option<PathGlob>,
+    /// Fail on findings of this severity or worse; absent means matching
+    /// findings never fail the run
+    pub fail_on: Option<Severity>,
+}
+
+impl PolicyRule {
+    pub fn matches(&self, vuln: &Vulnerability) -> bool {
+        self.class.as_ref().is_none_or(|class| *class == vuln.panic_class) &&
+            self.path.as_ref().is_none_or(|glob| glob.matcher.is_match(&vuln.file))
+    }
+
+    /// Selectors as written in the config, for gate messages
+    pub fn describe(&self) -> String {
+        let mut selectors = Vec::new();
+        if let Some(class) = &self.class {
+            selectors.push(format!("class {:?}", class));
+        }
+        if let Some(path) = &self.path {
+            selectors.push(format!("path {}", path.pattern));
+        }
+        if selectors.is_empty() {
+            "all findings".to_string()
+        } else {
+            selectors.join(", ")
+        }
+    }
+}
+
+/// Severity from context (`--rule-severity-from-exploitability`): a finding
+/// scores `base`, plus `untrusted` when untrusted input drives it
+/// (Cloudflare-class), `hot_path` in a loop or per-element closure,
+/// `amplification` when it poisons shared state, and `ffi_abort` in an
+/// `extern "C"` fn where unwinding aborts. The score then maps to Critical,
+/// High or Medium at the `*_at` thresholds, and to Low below them.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Exploitability {
+    /// Replace rule severities with the score (also set by the CLI flag)
+    pub enabled: bool,
+    pub base: u32,
+    pub untrusted: u32,
+    pub hot_path: u32,
+    pub amplification: u32,
+    pub ffi_abort: u32,
+    pub critical_at: u32,
+    pub high_at: u32,
+    pub medium_at: u32,
+}
+
+impl Default for Exploitability {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base: 1,
+            untrusted: 3,
+            hot_path: 2,
+            amplification: 3,
+            ffi_abort: 3,
+            critical_at: 6,
+            high_at: 4,
+            medium_at: 2,
+        }
+    }
+}
+
+impl Exploitability {
+    pub fn score(&self, vuln: &Vulnerability) -> u32 {
+        let factors = [
+            (vuln.panic_class == PanicClass::CloudflareClass, self.untrusted),
+            (vuln.hot_path, self.hot_path),
+            (vuln.panic_class == PanicClass::PanicAmplification, self.amplification),
+            (vuln.in_ffi, self.ffi_abort),
+        ];
+        self.base + factors.iter().filter(|(present, _)| *present).map(|(_, weight)| weight).sum::<u32>()
+    }
+
+    pub fn severity(&self, score: u32) -> Severity {
+        if score >= self.critical_at {
+            Severity::Critical
+        } else if score >= self.high_at {
+            Severity::High
+        } else if score >= self.medium_at {
+            Severity::Medium
+        } else {
+            Severity::Low
+        }
+    }
+}
+
+/// A path glob validated when the config is loaded
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PathGlob {
+    pub pattern: String,
+    matcher: GlobMatcher,
+}
+
+impl TryFrom<String> for PathGlob {
+    type Error = globset::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        let matcher = Glob::new(&pattern)?.compile_matcher();
+        Ok(Self { pattern, matcher })
+    }
+}
+
+impl From<PathGlob> for String {
+    fn from(glob: PathGlob) -> Self {
+        glob.pattern
+    }
+}
+
+impl Config {
+    /// Load `explicit`, or `.panic-audit.toml` if present. A missing default
+    /// file is not an error; a missing explicit one is.
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        let path = match explicit {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if !path.exists() {
+                    return Ok(Self::default());
+                }
+                path
+            }
+        };
+
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        let mut config: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid config {}", path.display()))?;
+        if let Some(id) = config.severity.keys().find(|id| !rules::all_rules().iter().any(|rule| rule.id == *id)) {
+            anyhow::bail!("Invalid config {}: unknown rule id `{}` in [severity]", path.display(), id);
+        }
+        tracing::debug!(path = %path.display(), "loaded config");
+        config.source = Some(path);
+        Ok(config)
+    }
+
+    pub fn is_trusted_fn(&self, name: &str) -> bool {
+        self.trusted_functions.iter().any(|pattern| wildcard_match(pattern, name))
+    }
+}
+
+/// Match `name` against a pattern where `*` stands for any run of characters
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,535 @@
+use crate::audit::{self, ParseFailure};
+use crate::config::Config;
+use crate::download::{self, Download, DownloadOptions};
+use crate::types::{Severity, Vulnerability};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Registry sources whose packages can be fetched from crates.io
+const CRATES_IO_SOURCES: [&str; 2] = [
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+/// Findings for every crates.io dependency in a lockfile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepsReport {
+    pub lockfile: String,
+    /// Riskiest first: most Critical, then most High findings
+    pub crates: Vec<CrateFindings>,
+    /// Dependencies that couldn't be downloaded or scanned
+    pub failed: Vec<FailedCrate>,
+    /// Path, git and other non-crates.io packages in the lockfile, not scanned
+    pub skipped: usize,
+    /// Locked versions published before `--since`, not scanned
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub older: Vec<DatedCrate>,
+    /// `name@version` whose findings failed the gate under `--fail-fast`,
+    /// ending the run early
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_at: Option<String>,
+    /// Dependencies left unscanned because the run stopped early
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub not_scanned: usize,
+    /// Dependency files that couldn't be read or parsed, as
+    /// `<name>-<version>/<path>`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unparsed: Vec<ParseFailure>,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrateFindings {
+    pub crate_name: String,
+    pub version: String,
+    pub critical: usize,
+    pub high: usize,
+    pub findings: Vec<Vulnerability>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedCrate {
+    pub crate_name: String,
+    pub version: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatedCrate {
+    pub crate_name: String,
+    pub version: String,
+    /// RFC 3339, as crates.io reports it
+    pub published_at: String,
+}
+
+/// `--summary-json`: per-crate counts and scores without the findings
+/// themselves, for dashboards sweeping many crates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepsSummary {
+    pub lockfile: String,
+    /// Lowest score first
+    pub crates: Vec<CrateSummary>,
+    pub failed: Vec<FailedCrate>,
+    pub skipped: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub older: Vec<DatedCrate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_at: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub not_scanned: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unparsed: Vec<ParseFailure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrateSummary {
+    pub name: String,
+    pub version: String,
+    /// 100 minus 10 per Critical, 5 per High, 2 per Medium and 1 per Low
+    /// finding, floored at 0
+    pub score: u32,
+    /// A (90+), B (75+), C (50+), D (25+) or F; C at best with a Critical
+    pub grade: char,
+    pub counts: SeverityCounts,
+    /// Report file of this crate in an `--output-dir` index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeverityCounts {
+    pub critical: usize,
+    pub high: usize,
+    pub medium: usize,
+    pub low: usize,
+}
+
+impl SeverityCounts {
+    pub fn of(findings: &[Vulnerability]) -> Self {
+        let count = |severity: Severity| findings.iter().filter(|v| v.severity == severity).count();
+        Self {
+            critical: count(Severity::Critical),
+            high: count(Severity::High),
+            medium: count(Severity::Medium),
+            low: count(Severity::Low),
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        let penalty = 10 * self.critical + 5 * self.high + 2 * self.medium + self.low;
+        100u32.saturating_sub(penalty.min(100) as u32)
+    }
+
+    /// Letter grade for the score. A single Critical can take a service
+    /// down, so a crate with one never grades above C however few it has.
+    pub fn grade(&self) -> char {
+        let grade = match self.score() {
+            90.. => 'A',
+            75..=89 => 'B',
+            50..=74 => 'C',
+            25..=49 => 'D',
+            _ => 'F',
+        };
+        if self.critical > 0 {
+            grade.max('C')
+        } else {
+            grade
+        }
+    }
+}
+
+/// Where `--deps` gets dependency sources and publish dates from: crates.io,
+/// or canned crates in tests
+pub trait CrateSource {
+    /// Sources, unpacked under `<name>-<version>/` in the returned directory
+    fn download(&self, name: &str, version: &str) -> Result<Download>;
+    /// When this version was published
+    fn published_at(&self, name: &str, version: &str) -> Result<SystemTime>;
+}
+
+/// The crates.io registry, fetched with these download options
+pub struct CratesIo(pub DownloadOptions);
+
+impl CrateSource for CratesIo {
+    fn download(&self, name: &str, version: &str) -> Result<Download> {
+        download::download_crate(name, version, &self.0)
+    }
+
+    fn published_at(&self, name: &str, version: &str) -> Result<SystemTime> {
+        parse_timestamp(&download::published_at(name, version)?)
+    }
+}
+
+/// Whether one crate's findings fail the gate
+pub type GateCheck<'a> = &'a dyn Fn(&[Vulnerability]) -> bool;
+
+/// Which dependencies to scan
+#[derive(Default)]
+pub struct DepsOptions<'a> {
+    /// `--since`: skip versions published before this
+    pub since: Option<SystemTime>,
+    /// `--fail-fast`: stop after the first crate whose findings this fails
+    pub fail_fast: Option<GateCheck<'a>>,
+}
+
+/// An RFC 3339 timestamp in UTC, with `Z` or the `+00:00` crates.io uses
+pub fn parse_timestamp(text: &str) -> Result<SystemTime> {
+    let utc = text.strip_suffix("+00:00").map(|time| format!("{}Z", time));
+    humantime::parse_rfc3339(utc.as_deref().unwrap_or(text))
+        .with_context(|| format!("Not an RFC 3339 UTC timestamp: {}", text))
+}
+
+impl DepsReport {
+    /// Findings of all crates, for gates
+    pub fn all_findings(&self) -> Vec<Vulnerability> {
+        self.crates.iter().flat_map(|krate| krate.findings.iter().cloned()).collect()
+    }
+
+    /// The `--summary-json` view: counts, score and grade per crate
+    pub fn summary(&self) -> DepsSummary {
+        let mut crates: Vec<_> = self
+            .crates
+            .iter()
+            .map(|krate| {
+                let counts = SeverityCounts::of(&krate.findings);
+                CrateSummary {
+                    name: krate.crate_name.clone(),
+                    version: krate.version.clone(),
+                    score: counts.score(),
+                    grade: counts.grade(),
+                    counts,
+                    report: None,
+                }
+            })
+            .collect();
+        crates.sort_by_key(|krate| krate.score);
+
+        DepsSummary {
+            lockfile: self.lockfile.clone(),
+            crates,
+            failed: self.failed.clone(),
+            skipped: self.skipped,
+            older: self.older.clone(),
+            stopped_at: self.stopped_at.clone(),
+            not_scanned: self.not_scanned,
+            unparsed: self.unparsed.clone(),
+        }
+    }
+}
+
+/// `name@version` of each crates.io package in `Cargo.lock`, deduplicated and
+/// sorted, plus how many packages from other sources were left out
+pub fn read_lockfile(path: &Path) -> Result<(Vec<(String, String)>, usize)> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let lockfile: toml::Value = toml::from_str(&text)
+        .with_context(|| format!("Invalid {}", path.display()))?;
+
+    let packages = lockfile
+        .get("package")
+        .and_then(|packages| packages.as_array())
+        .with_context(|| format!("No [[package]] entries in {}", path.display()))?;
+
+    let mut crates = BTreeSet::new();
+    let mut skipped = 0;
+    for package in packages {
+        let field = |key: &str| package.get(key).and_then(|value| value.as_str());
+        match (field("name"), field("version"), field("source")) {
+            (Some(name), Some(version), Some(source)) if CRATES_IO_SOURCES.contains(&source) => {
+                crates.insert((name.to_string(), version.to_string()));
+            }
+            _ => skipped += 1,
+        }
+    }
+
+    Ok((crates.into_iter().collect(), skipped))
+}
+
+/// Download and scan every crates.io dependency in `lockfile`. A crate that
+/// fails to download or unpack is recorded in `failed`; the rest still run.
+pub fn audit_lockfile(
+    lockfile: &Path,
+    config: &Config,
+    source: &dyn CrateSource,
+    options: &DepsOptions,
+) -> Result<DepsReport> {
+    let (locked, skipped) = read_lockfile(lockfile)?;
+    eprintln!("\n🔗 Auditing {} dependencies from {}", locked.len(), lockfile.display());
+
+    let mut crates = Vec::new();
+    let mut failed = Vec::new();
+    let mut older = Vec::new();
+    let mut stopped_at = None;
+    let mut not_scanned = 0;
+    let mut unparsed = Vec::new();
+    let total = locked.len();
+    for (index, (name, version)) in locked.into_iter().enumerate() {
+        let _span = tracing::info_span!("dependency", name = %name, version = %version).entered();
+        if let Some(since) = options.since {
+            // A version we can't date is scanned rather than silently dropped
+            match source.published_at(&name, &version) {
+                Ok(published) if published < since => {
+                    let published_at = humantime::format_rfc3339_seconds(published).to_string();
+                    older.push(DatedCrate { crate_name: name, version, published_at });
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("⚠️  No publish date for {} v{}, scanning it: {:#}", name, version, e),
+            }
+        }
+        match audit_dependency(&name, &version, config, source) {
+            Ok(scan) => {
+                let findings = scan.findings;
+                unparsed.extend(scan.unparsed.into_iter().map(|failure| ParseFailure {
+                    file: format!("{}-{}/{}", name, version, failure.file),
+                    ..failure
+                }));
+                let count = |severity: Severity| findings.iter().filter(|v| v.severity == severity).count();
+                let fails = options.fail_fast.is_some_and(|fails| fails(&findings));
+                if fails {
+                    stopped_at = Some(format!("{}@{}", name, version));
+                }
+                crates.push(CrateFindings {
+                    critical: count(Severity::Critical),
+                    high: count(Severity::High),
+                    crate_name: name,
+                    version,
+                    findings,
+                });
+            }
+            Err(e) => {
+                eprintln!("⚠️  Skipping {} v{}: {:#}", name, version, e);
+                failed.push(FailedCrate { crate_name: name, version, error: format!("{:#}", e) });
+            }
+        }
+        if stopped_at.is_some() {
+            not_scanned = total - index - 1;
+            break;
+        }
+    }
+
+    // Stable sort: ties stay in name order
+    crates.sort_by_key(|krate| std::cmp::Reverse((krate.critical, krate.high)));
+
+    Ok(DepsReport {
+        lockfile: lockfile.display().to_string(),
+        crates,
+        failed,
+        skipped,
+        older,
+        stopped_at,
+        not_scanned,
+        unparsed,
+    })
+}
+
+fn audit_dependency(name: &str, version: &str, config: &Config, source: &dyn CrateSource) -> Result<audit::Scan> {
+    let download = source.download(name, version)?;
+    // Archives unpack into `<name>-<version>/`
+    let root = download.path().join(format!("{}-{}", name, version));
+    let scan = audit::scan_directory(&root, name, config);
+    download.close()?;
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    const UNWRAP: &str = "pub fn load() -> String { std::fs::read_to_string(\"config.toml\").unwrap() }\n";
+    const CLEAN: &str = "pub fn add(a: u32, b: u32) -> Option<u32> { a.checked_add(b) }\n";
+
+    /// Canned crates: name, version, publish date and `src/lib.rs`
+    #[derive(Default)]
+    struct FakeSource {
+        crates: Vec<(&'static str, &'static str, &'static str, &'static str)>,
+        downloaded: RefCell<Vec<String>>,
+    }
+
+    impl FakeSource {
+        fn new(crates: &[(&'static str, &'static str, &'static str, &'static str)]) -> Self {
+            Self { crates: crates.to_vec(), ..Self::default() }
+        }
+
+        fn find(&self, name: &str, version: &str) -> Result<&(&'static str, &'static str, &'static str, &'static str)> {
+            self.crates
+                .iter()
+                .find(|krate| krate.0 == name && krate.1 == version)
+                .with_context(|| format!("no {} v{}", name, version))
+        }
+
+        /// A lockfile listing every canned crate, from crates.io
+        fn lockfile(&self, dir: &Path) -> std::path::PathBuf {
+            let mut text = String::from("version = 3\n");
+            for (name, version, ..) in &self.crates {
+                text += &format!(
+                    "\n[[package]]\nname = \"{}\"\nversion = \"{}\"\nsource = \"{}\"\n",
+                    name, version, CRATES_IO_SOURCES[0]
+                );
+            }
+            let path = dir.join("Cargo.lock");
+            std::fs::write(&path, text).unwrap();
+            path
+        }
+    }
+
+    impl CrateSource for FakeSource {
+        fn download(&self, name: &str, version: &str) -> Result<Download> {
+            let (.., lib) = self.find(name, version)?;
+            self.downloaded.borrow_mut().push(name.to_string());
+            let dir = tempfile::tempdir()?;
+            let src = dir.path().join(format!("{}-{}", name, version)).join("src");
+            std::fs::create_dir_all(&src)?;
+            std::fs::write(src.join("lib.rs"), lib)?;
+            Ok(Download::Temp(dir))
+        }
+
+        fn published_at(&self, name: &str, version: &str) -> Result<SystemTime> {
+            parse_timestamp(self.find(name, version)?.2)
+        }
+    }
+
+    fn audit(source: &FakeSource, options: &DepsOptions) -> DepsReport {
+        let dir = tempfile::tempdir().unwrap();
+        audit_lockfile(&source.lockfile(dir.path()), &Config::default(), source, options).unwrap()
+    }
+
+    #[test]
+    fn parses_crates_io_timestamps() {
+        let z = parse_timestamp("2024-06-01T12:00:00Z").unwrap();
+        let offset = parse_timestamp("2024-06-01T12:00:00.123456+00:00").unwrap();
+        assert_eq!(offset.duration_since(z).unwrap().as_micros(), 123_456);
+        assert!(parse_timestamp("2024-06-01T12:00:00+02:00").is_err());
+        assert!(parse_timestamp("yesterday").is_err());
+    }
+
+    #[test]
+    fn since_skips_and_lists_older_versions() {
+        let source = FakeSource::new(&[
+            ("fresh", "2.0.0", "2024-07-01T08:00:00.5+00:00", UNWRAP),
+            ("stale", "1.0.0", "2021-03-04T05:06:07+00:00", UNWRAP),
+        ]);
+        let since = parse_timestamp("2024-06-01T00:00:00Z").unwrap();
+        let report = audit(&source, &DepsOptions { since: Some(since), ..DepsOptions::default() });
+
+        assert_eq!(*source.downloaded.borrow(), ["fresh"]);
+        assert_eq!(report.crates.len(), 1);
+        assert_eq!(report.crates[0].crate_name, "fresh");
+        assert_eq!(
+            report.older,
+            [DatedCrate {
+                crate_name: "stale".to_string(),
+                version: "1.0.0".to_string(),
+                published_at: "2021-03-04T05:06:07Z".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn unparseable_dependency_files_are_kept() {
+        let source = FakeSource::new(&[
+            ("broken", "0.1.0", "2020-01-01T00:00:00Z", "pub fn broken( {\n"),
+            ("fine", "1.0.0", "2020-01-01T00:00:00Z", CLEAN),
+        ]);
+        let report = audit(&source, &DepsOptions::default());
+
+        assert_eq!(report.unparsed.len(), 1, "{:#?}", report.unparsed);
+        assert_eq!(report.unparsed[0].file, "broken-0.1.0/src/lib.rs");
+        assert_eq!(report.crates.len(), 2);
+        // --summary-json and the --output-dir index keep them too
+        assert_eq!(report.summary().unparsed, report.unparsed);
+    }
+
+    #[test]
+    fn without_since_every_crate_is_scanned() {
+        let source = FakeSource::new(&[
+            ("clean", "0.1.0", "2020-01-01T00:00:00Z", CLEAN),
+            ("risky", "0.2.0", "2020-01-01T00:00:00Z", UNWRAP),
+        ]);
+        let report = audit(&source, &DepsOptions::default());
+
+        assert_eq!(*source.downloaded.borrow(), ["clean", "risky"]);
+        assert!(report.older.is_empty());
+        // Riskiest first
+        assert_eq!(report.crates[0].crate_name, "risky");
+        assert_eq!(report.crates[0].critical, 1);
+    }
+
+    #[test]
+    fn fail_fast_stops_before_later_crates() {
+        let source = FakeSource::new(&[
+            ("alpha", "1.0.0", "2024-01-01T00:00:00Z", CLEAN),
+            ("bravo", "1.0.0", "2024-01-01T00:00:00Z", UNWRAP),
+            ("charlie", "1.0.0", "2024-01-01T00:00:00Z", UNWRAP),
+            ("delta", "1.0.0", "2024-01-01T00:00:00Z", CLEAN),
+        ]);
+        let has_critical = |findings: &[Vulnerability]| findings.iter().any(|v| v.severity == Severity::Critical);
+        let report = audit(&source, &DepsOptions { fail_fast: Some(&has_critical), ..DepsOptions::default() });
+
+        assert_eq!(*source.downloaded.borrow(), ["alpha", "bravo"]);
+        assert_eq!(report.stopped_at.as_deref(), Some("bravo@1.0.0"));
+        assert_eq!(report.not_scanned, 2);
+        assert_eq!(report.crates.len(), 2);
+        assert_eq!(report.crates[0].crate_name, "bravo");
+        assert_eq!(report.crates[0].critical, 1);
+    }
+
+    #[test]
+    fn fail_fast_scans_everything_when_nothing_fails() {
+        let source = FakeSource::new(&[
+            ("alpha", "1.0.0", "2024-01-01T00:00:00Z", CLEAN),
+            ("bravo", "1.0.0", "2024-01-01T00:00:00Z", CLEAN),
+        ]);
+        let never = |_: &[Vulnerability]| false;
+        let report = audit(&source, &DepsOptions { fail_fast: Some(&never), ..DepsOptions::default() });
+
+        assert_eq!(source.downloaded.borrow().len(), 2);
+        assert_eq!((report.stopped_at, report.not_scanned), (None, 0));
+    }
+
+    #[test]
+    fn summary_scores_crates_without_findings() {
+        let source = FakeSource::new(&[
+            ("clean", "0.1.0", "2020-01-01T00:00:00Z", CLEAN),
+            ("risky", "0.2.0", "2020-01-01T00:00:00Z", UNWRAP),
+        ]);
+        let summary = audit(&source, &DepsOptions::default()).summary();
+
+        assert_eq!(summary.crates[0].name, "risky");
+        assert_eq!(summary.crates[0].counts, SeverityCounts { critical: 1, ..SeverityCounts::default() });
+        assert_eq!((summary.crates[0].score, summary.crates[0].grade), (90, 'C'));
+        assert_eq!((summary.crates[1].score, summary.crates[1].grade), (100, 'A'));
+
+        let json = serde_json::to_value(&summary).unwrap();
+        for krate in json["crates"].as_array().unwrap() {
+            assert!(krate.get("findings").is_none(), "{}", krate);
+            assert!(krate["counts"]["critical"].is_u64(), "{}", krate);
+        }
+        assert!(!json.to_string().contains("\"findings\""));
+    }
+
+    #[test]
+    fn scores_floor_at_zero_and_grade_by_band() {
+        let counts = |critical, high, medium, low| SeverityCounts { critical, high, medium, low };
+        assert_eq!(counts(0, 2, 1, 1).score(), 87);
+        assert_eq!(counts(20, 0, 0, 0).score(), 0);
+        // Scores 100, 90, 89, 75, 74, 50, 49, 25, 24 and 0
+        let grades: String = [0, 10, 11, 25, 26, 50, 51, 75, 76, 100]
+            .into_iter()
+            .map(|low| counts(0, 0, 0, low).grade())
+            .collect();
+        assert_eq!(grades, "AABBCCDDFF");
+
+        // A Critical caps the grade at C, but doesn't lift a worse one
+        assert_eq!(counts(1, 0, 0, 0).grade(), 'C');
+        assert_eq!(counts(1, 0, 0, 35).grade(), 'C');
+        assert_eq!(counts(3, 5, 0, 0).grade(), 'D');
+    }
+}
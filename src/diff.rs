@@ -0,0 +1,269 @@
+use crate::audit;
+use crate::config::Config;
+use crate::download::{self, DownloadOptions};
+use crate::types::{Severity, Vulnerability};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Findings that differ between two scans of the same crate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comparison {
+    pub crate_name: String,
+    /// Version the local tree was compared against
+    pub baseline_version: String,
+    /// In the local tree but not in the baseline
+    pub new: Vec<Vulnerability>,
+    /// In the baseline but no longer in the local tree
+    pub fixed: Vec<Vulnerability>,
+    pub unchanged: usize,
+    pub baseline_delta: BaselineDelta,
+}
+
+/// Findings that differ between two rule configurations over the same scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleComparison {
+    pub crate_name: String,
+    /// Config used for the main scan (`--config` or the default), if any
+    pub current_config: Option<String>,
+    /// Config given to `--compare-rules`
+    pub other_config: String,
+    /// Produced (at this severity) only by the current config
+    pub only_current: Vec<Vulnerability>,
+    /// Produced (at this severity) only by the other config
+    pub only_other: Vec<Vulnerability>,
+    pub unchanged: usize,
+}
+
+/// One-line trend against a baseline: what was introduced, what was resolved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineDelta {
+    pub introduced: usize,
+    pub introduced_critical: usize,
+    pub introduced_high: usize,
+    pub resolved: usize,
+    /// `introduced - resolved`; negative means the panic profile improved
+    pub net: i64,
+}
+
+impl BaselineDelta {
+    /// `new` findings against `resolved` ones that are gone
+    pub fn new(new: &[Vulnerability], resolved: usize) -> Self {
+        let count = |severity: Severity| new.iter().filter(|v| v.severity == severity).count();
+        Self {
+            introduced: new.len(),
+            introduced_critical: count(Severity::Critical),
+            introduced_high: count(Severity::High),
+            resolved,
+            net: new.len() as i64 - resolved as i64,
+        }
+    }
+
+    /// `Introduced 2 new (1 Critical), resolved 3, net -1`
+    pub fn summary(&self) -> String {
+        let mut worst = Vec::new();
+        if self.introduced_critical > 0 {
+            worst.push(format!("{} Critical", self.introduced_critical));
+        }
+        if self.introduced_high > 0 {
+            worst.push(format!("{} High", self.introduced_high));
+        }
+        let worst = if worst.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", worst.join(", "))
+        };
+
+        format!(
+            "Introduced {} new{}, resolved {}, net {:+}",
+            self.introduced, worst, self.resolved, self.net
+        )
+    }
+}
+
+/// Match findings by id (rule, file, normalized code), so moved
+/// lines don't count as changes. Repeated identical findings are matched
+/// one-for-one.
+pub fn diff(
+    baseline: Vec<Vulnerability>,
+    current: Vec<Vulnerability>,
+) -> (Vec<Vulnerability>, Vec<Vulnerability>, usize) {
+    diff_by(baseline, current, |vuln| vuln.id.clone())
+}
+
+/// `diff` over an arbitrary identity
+fn diff_by(
+    baseline: Vec<Vulnerability>,
+    current: Vec<Vulnerability>,
+    key: impl Fn(&Vulnerability) -> String,
+) -> (Vec<Vulnerability>, Vec<Vulnerability>, usize) {
+    let mut remaining: HashMap<String, usize> = HashMap::new();
+    for vuln in &baseline {
+        *remaining.entry(key(vuln)).or_default() += 1;
+    }
+
+    let mut new = Vec::new();
+    let mut unchanged = 0;
+    for vuln in current {
+        match remaining.get_mut(&key(&vuln)) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                unchanged += 1;
+            }
+            _ => new.push(vuln),
+        }
+    }
+
+    let fixed = baseline
+        .into_iter()
+        .filter(|vuln| match remaining.get_mut(&key(vuln)) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        })
+        .collect();
+
+    (new, fixed, unchanged)
+}
+
+/// Scan the latest crates.io release of the crate at `crate_root` and diff
+/// `local` findings (a scan of `scan_path`, the root or a directory in it)
+/// against the same directory of the release
+pub fn compare_with_published(
+    crate_root: &Path,
+    scan_path: &Path,
+    local: Vec<Vulnerability>,
+    config: &Config,
+    download: &DownloadOptions,
+) -> Result<Comparison> {
+    let crate_name = read_package_name(crate_root)?;
+    let subdir = scan_path
+        .canonicalize()?
+        .strip_prefix(crate_root.canonicalize()?)
+        .map(Path::to_path_buf)
+        .with_context(|| format!("{} is not inside crate root {}", scan_path.display(), crate_root.display()))?;
+
+    eprintln!("\n🔎 Finding latest published version of {}...", crate_name);
+    let version = download::get_latest_version(&crate_name)?;
+
+    let download = download::download_crate(&crate_name, &version, download)?;
+    // Archives unpack into `<name>-<version>/`; scan from there so file
+    // paths line up with the local tree
+    let published_root = download.path().join(format!("{}-{}", crate_name, version)).join(subdir);
+    let published = audit::scan_directory(&published_root, &crate_name, config).findings;
+    download.close()?;
+
+    Ok(compare(crate_name, version, published, local))
+}
+
+/// New and fixed findings of `local` against a scan of the published release
+pub fn compare(crate_name: String, version: String, published: Vec<Vulnerability>, local: Vec<Vulnerability>) -> Comparison {
+    let (new, fixed, unchanged) = diff(published, local);
+    Comparison {
+        crate_name,
+        baseline_version: version,
+        baseline_delta: BaselineDelta::new(&new, fixed.len()),
+        new,
+        fixed,
+        unchanged,
+    }
+}
+
+/// Rescan `path` with `other` and diff against `current`, the findings of
+/// the scan with `config`. A finding whose severity changed shows up once on
+/// each side.
+pub fn compare_rules(
+    path: &Path,
+    crate_name: &str,
+    current: Vec<Vulnerability>,
+    config: &Config,
+    other: &Config,
+) -> RuleComparison {
+    eprintln!("\n📏 Rescanning with the other rule configuration...");
+    let other_findings = audit::scan_directory(path, crate_name, other).findings;
+
+    let key = |vuln: &Vulnerability| format!("{}:{:?}", vuln.id, vuln.severity);
+    let (only_current, only_other, unchanged) = diff_by(other_findings, current, key);
+    let label = |config: &Config| config.source.as_ref().map(|path| path.display().to_string());
+    RuleComparison {
+        crate_name: crate_name.to_string(),
+        current_config: label(config),
+        other_config: label(other).unwrap_or_default(),
+        only_current,
+        only_other,
+        unchanged,
+    }
+}
+
+/// `package.name` from the `Cargo.toml` at `path`
+pub fn read_package_name(path: &Path) -> Result<String> {
+    let manifest_path = path.join("Cargo.toml");
+    let text = std::fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let manifest: toml::Value = toml::from_str(&text)
+        .with_context(|| format!("Invalid {}", manifest_path.display()))?;
+
+    manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .map(|name| name.to_string())
+        .with_context(|| format!("No [package] name in {}", manifest_path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLISHED: &str = r#"
+pub fn load() -> String {
+    std::fs::read_to_string("data.txt").unwrap()
+}
+
+pub fn header(buf: &[u8]) -> u8 {
+    buf[0]
+}
+"#;
+
+    /// `load` moved down, `header` fixed, `port` new
+    const LOCAL: &str = r#"
+/// Loads the data file
+pub fn load() -> String {
+    std::fs::read_to_string("data.txt").unwrap()
+}
+
+pub fn port(text: &str) -> u16 {
+    text.parse().unwrap()
+}
+"#;
+
+    fn scan(dir: &Path, lib: &str) -> Vec<Vulnerability> {
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"demo\"\nversion = \"0.2.0\"\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+        audit::scan_directory(dir, "demo", &Config::default()).findings
+    }
+
+    #[test]
+    fn local_tree_against_a_published_scan() {
+        let temp = tempfile::tempdir().unwrap();
+        let published = scan(&temp.path().join("demo-0.1.0"), PUBLISHED);
+        let local_root = temp.path().join("local");
+        let local = scan(&local_root, LOCAL);
+
+        let crate_name = read_package_name(&local_root).unwrap();
+        let comparison = compare(crate_name, "0.1.0".to_string(), published, local);
+
+        assert_eq!(comparison.crate_name, "demo");
+        assert_eq!(comparison.unchanged, 1, "{:#?}", comparison);
+        assert_eq!(comparison.new.len(), 1, "{:#?}", comparison);
+        assert!(comparison.new[0].code.contains("parse"), "{:#?}", comparison.new);
+        assert_eq!(comparison.fixed.len(), 1, "{:#?}", comparison);
+        assert!(comparison.fixed[0].code.contains("buf"), "{:#?}", comparison.fixed);
+        assert_eq!(comparison.baseline_delta.net, 0);
+        assert!(comparison.baseline_delta.summary().starts_with("Introduced 1 new"));
+    }
+}
@@ -1,9 +1,11 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use colored::*;
 use flate2::read::GzDecoder;
-use std::fs;
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
 use tar::Archive;
+use tempfile::TempDir;
 
 pub fn get_latest_version(crate_name: &str) -> Result<String> {
     let url = format!("https://crates.io/api/v1/crates/{}", crate_name);
@@ -21,8 +23,91 @@ pub fn get_latest_version(crate_name: &str) -> Result<String> {
         .ok_or_else(|| anyhow::anyhow!("Could not find latest version"))
 }
 
-pub fn download_crate(name: &str, version: &str) -> Result<PathBuf> {
-    println!("{}", format!("📥 Downloading {} v{}...", name, version).cyan());
+/// When crates.io published this version (its `created_at`, RFC 3339)
+pub fn published_at(name: &str, version: &str) -> Result<String> {
+    let url = format!("https://crates.io/api/v1/crates/{}/{}", name, version);
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(60))
+        .build()?;
+    let response: serde_json::Value = client
+        .get(&url)
+        .header("User-Agent", "cargo-panic-audit/0.5.2")
+        .send()
+        .context("Failed to fetch crate metadata")?
+        .json()
+        .context("Invalid crate metadata from crates.io")?;
+
+    response["version"]["created_at"]
+        .as_str()
+        .map(|s| s.to_string())
+        .with_context(|| format!("crates.io lists no publish date for {} v{}", name, version))
+}
+
+/// Extracted sources of a downloaded crate: a directory holding the
+/// archive's `<name>-<version>/`
+pub enum Download {
+    /// Entry in the download cache, kept for later runs
+    Cached(PathBuf),
+    /// Removed by `close`, or when dropped
+    Temp(TempDir),
+}
+
+impl Download {
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Cached(path) => path,
+            Self::Temp(temp_dir) => temp_dir.path(),
+        }
+    }
+
+    /// Delete temporary sources; cached ones stay
+    pub fn close(self) -> Result<()> {
+        if let Self::Temp(temp_dir) = self {
+            eprintln!("\n🧹 Cleaning up...");
+            temp_dir.close()?;
+        }
+        Ok(())
+    }
+}
+
+/// How crates are fetched: where to cache them and whether to check them
+pub struct DownloadOptions {
+    pub cache: Option<PathBuf>,
+    /// Compare the archive's SHA-256 with the checksum crates.io publishes
+    /// before unpacking it
+    pub verify_checksum: bool,
+}
+
+/// `$XDG_CACHE_HOME/cargo-panic-audit`, else `~/.cache/cargo-panic-audit`;
+/// none when neither variable is set
+pub fn default_cache_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("cargo-panic-audit"))
+}
+
+/// Download, verify and extract a crate. With a cache directory, sources are
+/// kept in `<cache>/<name>-<version>/` and reused instead of downloaded again.
+/// Otherwise they go to a fresh, uniquely named directory under the system
+/// temp dir, removed when the returned guard is dropped, so concurrent runs
+/// never collide and errors don't leave litter. Unverified archives are never
+/// cached.
+pub fn download_crate(name: &str, version: &str, options: &DownloadOptions) -> Result<Download> {
+    let key = format!("{}-{}", name, version);
+    // A name or version that isn't a single path component can't be a cache key
+    let is_plain = matches!(Path::new(&key).components().collect::<Vec<_>>().as_slice(), [Component::Normal(_)]);
+    let entry = options.cache.as_deref().filter(|_| is_plain).map(|dir| dir.join(&key));
+    if let Some(entry) = entry.as_ref().filter(|entry| entry.is_dir()) {
+        eprintln!("{}", format!("📦 Using cached {} v{}", name, version).cyan());
+        return Ok(Download::Cached(entry.clone()));
+    }
+    // An unverified archive is used for this run only
+    let entry = entry.filter(|_| options.verify_checksum);
+
+    eprintln!("{}", format!("📥 Downloading {} v{}...", name, version).cyan());
 
     let url = format!(
         "https://crates.io/api/v1/crates/{}/{}/download",
@@ -44,15 +129,255 @@ pub fn download_crate(name: &str, version: &str) -> Result<PathBuf> {
     }
 
     let bytes = response.bytes()?;
-    
-    println!("📦 Extracting...");
 
-    let temp_dir = PathBuf::from(format!("./temp_{}_{}", name, version));
-    fs::create_dir_all(&temp_dir)?;
+    if options.verify_checksum {
+        let expected = published_checksum(&client, name, version)?;
+        let actual: String = Sha256::digest(&bytes).iter().map(|byte| format!("{:02x}", byte)).collect();
+        if !actual.eq_ignore_ascii_case(&expected) {
+            bail!(
+                "Checksum mismatch for {} v{}: crates.io lists {}, the download hashes to {}; \
+                 refusing to unpack (--skip-checksum skips this check)",
+                name, version, expected, actual
+            );
+        }
+    }
+
+    eprintln!("📦 Extracting...");
+
+    let Some(entry) = entry else {
+        return unpack_to_temp(&bytes[..], &key);
+    };
+
+    // Extract next to the entry and rename it into place, so an interrupted
+    // run never leaves a partial entry that later runs would trust
+    let cache = entry.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(cache)
+        .with_context(|| format!("Failed to create cache directory {}", cache.display()))?;
+    let staging = tempfile::Builder::new()
+        .prefix(&format!(".{}-", key))
+        .tempdir_in(cache)
+        .context("Failed to create cache staging directory")?;
 
     let tar = GzDecoder::new(&bytes[..]);
-    let mut archive = Archive::new(tar);
-    archive.unpack(&temp_dir)?;
+    extract_limited(Archive::new(tar), staging.path())?;
+
+    // Losing the race to a concurrent run is fine: its entry is just as complete
+    if let Err(e) = std::fs::rename(staging.path(), &entry) {
+        if !entry.is_dir() {
+            return Err(e).with_context(|| format!("Failed to store {} in the cache", entry.display()));
+        }
+    }
 
-    Ok(temp_dir)
+    Ok(Download::Cached(entry))
+}
+
+/// Unpack a `.crate` file from disk (e.g. fetched from an internal mirror)
+/// without touching the network
+pub fn unpack_local_crate(path: &Path) -> Result<Download> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    eprintln!("📦 Extracting {}...", path.display());
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("crate");
+    unpack_to_temp(file, stem).with_context(|| format!("Failed to unpack {}", path.display()))
+}
+
+/// `serde_json-1.0.108.crate` -> (`serde_json`, `1.0.108`). Names may contain
+/// `-` but no part of one starts with a digit after it, so the version starts
+/// at the first `-` followed by a digit.
+pub fn split_crate_file_name(path: &Path) -> Option<(String, String)> {
+    let stem = path.file_stem()?.to_str()?;
+    let (dash, _) = stem
+        .match_indices('-')
+        .find(|(i, _)| stem[i + 1..].starts_with(|c: char| c.is_ascii_digit()))?;
+    Some((stem[..dash].to_string(), stem[dash + 1..].to_string()))
+}
+
+/// Extract a gzipped crate archive into a fresh temporary directory
+fn unpack_to_temp<R: Read>(gzipped: R, label: &str) -> Result<Download> {
+    let temp_dir = tempfile::Builder::new()
+        .prefix(&format!("cargo-panic-audit-{}-", label))
+        .tempdir()
+        .context("Failed to create temporary directory")?;
+
+    extract_limited(Archive::new(GzDecoder::new(gzipped)), temp_dir.path())?;
+    Ok(Download::Temp(temp_dir))
+}
+
+/// SHA-256 of the `.crate` archive as recorded by crates.io
+fn published_checksum(client: &reqwest::blocking::Client, name: &str, version: &str) -> Result<String> {
+    let url = format!("https://crates.io/api/v1/crates/{}/{}", name, version);
+    let response: serde_json::Value = client
+        .get(&url)
+        .header("User-Agent", "cargo-panic-audit/0.5.2")
+        .send()
+        .context("Failed to fetch crate checksum")?
+        .json()
+        .context("Invalid crate metadata from crates.io")?;
+
+    response["version"]["checksum"]
+        .as_str()
+        .map(|s| s.to_string())
+        .with_context(|| format!("crates.io lists no checksum for {} v{}", name, version))
+}
+
+/// Upper bound on the total size of extracted files. crates.io caps the
+/// compressed upload at 10 MiB; this leaves room for very compressible source.
+const MAX_EXTRACTED_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Upper bound on the number of archive entries
+const MAX_ENTRIES: usize = 50_000;
+
+/// Unpack an untrusted tarball into `dest`, refusing decompression bombs and
+/// entries that would land outside `dest` (absolute paths, `..`, links).
+fn extract_limited<R: Read>(mut archive: Archive<R>, dest: &Path) -> Result<()> {
+    let mut total_bytes: u64 = 0;
+
+    for (index, entry) in archive.entries().context("Failed to read archive")?.enumerate() {
+        if index >= MAX_ENTRIES {
+            bail!("Archive has more than {} entries; refusing to extract", MAX_ENTRIES);
+        }
+
+        let mut entry = entry.context("Corrupt archive entry")?;
+        let path = entry.path().context("Invalid path in archive")?.into_owned();
+
+        let escapes = path.components().any(|c| {
+            matches!(c, Component::ParentDir | Component::RootDir | Component::Prefix(_))
+        });
+        if escapes {
+            bail!("Archive entry {} escapes the extraction directory", path.display());
+        }
+
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            bail!("Archive entry {} is a link; refusing to extract", path.display());
+        }
+
+        total_bytes += entry.size();
+        if total_bytes > MAX_EXTRACTED_BYTES {
+            bail!(
+                "Archive expands to more than {} MiB; refusing to extract",
+                MAX_EXTRACTED_BYTES / (1024 * 1024)
+            );
+        }
+
+        if !entry.unpack_in(dest).with_context(|| format!("Failed to extract {}", path.display()))? {
+            bail!("Archive entry {} escapes the extraction directory", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    /// A gzipped tarball holding `files`
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let entries: Vec<_> = files
+            .iter()
+            .map(|(path, content)| raw_entry(path, tar::EntryType::Regular, content.len() as u64, content))
+            .collect();
+        gzip_tar(&entries)
+    }
+
+    /// A tar header with `path` written verbatim, bypassing the builder's
+    /// own path checks, as a malicious archive would
+    fn raw_entry(path: &str, kind: tar::EntryType, size: u64, content: &str) -> (tar::Header, Vec<u8>) {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_entry_type(kind);
+        header.set_size(size);
+        header.set_mode(0o644);
+        if kind.is_symlink() {
+            header.set_link_name("/etc/passwd").unwrap();
+        }
+        header.set_cksum();
+        (header, content.as_bytes().to_vec())
+    }
+
+    fn gzip_tar(entries: &[(tar::Header, Vec<u8>)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        for (header, content) in entries {
+            builder.append(header, &content[..]).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn unpack_error(bytes: &[u8]) -> String {
+        match unpack_to_temp(bytes, "malicious") {
+            Ok(download) => panic!("unpacked into {}", download.path().display()),
+            Err(e) => format!("{:#}", e),
+        }
+    }
+
+    #[test]
+    fn concurrent_unpacks_of_the_same_crate_get_their_own_directories() {
+        let bytes = archive(&[("demo-1.0.0/src/lib.rs", "pub fn f() {}\n")]);
+
+        let downloads: Vec<Download> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| unpack_to_temp(&bytes[..], "demo-1.0.0").unwrap()))
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+
+        let dirs: BTreeSet<PathBuf> = downloads.iter().map(|download| download.path().to_path_buf()).collect();
+        assert_eq!(dirs.len(), downloads.len());
+        for dir in &dirs {
+            assert!(dir.starts_with(std::env::temp_dir()), "{}", dir.display());
+            assert!(dir.join("demo-1.0.0/src/lib.rs").is_file());
+        }
+
+        // Dropping the guards removes every directory, even without close()
+        drop(downloads);
+        assert!(dirs.iter().all(|dir| !dir.exists()));
+    }
+
+    #[test]
+    fn rejects_entries_that_escape_the_directory() {
+        let outside = std::env::temp_dir().join("cargo-panic-audit-escape-test.rs");
+        let traversal = gzip_tar(&[raw_entry(
+            "demo-1.0.0/../../../cargo-panic-audit-escape-test.rs",
+            tar::EntryType::Regular,
+            4,
+            "evil",
+        )]);
+        assert!(unpack_error(&traversal).contains("escapes the extraction directory"));
+        assert!(!outside.exists());
+
+        let absolute = gzip_tar(&[raw_entry("/tmp/cargo-panic-audit-absolute.rs", tar::EntryType::Regular, 4, "evil")]);
+        assert!(unpack_error(&absolute).contains("escapes the extraction directory"));
+    }
+
+    #[test]
+    fn rejects_links() {
+        let symlink = gzip_tar(&[raw_entry("demo-1.0.0/src/lib.rs", tar::EntryType::Symlink, 0, "")]);
+        assert!(unpack_error(&symlink).contains("is a link"));
+    }
+
+    #[test]
+    fn rejects_decompression_bombs() {
+        // The header claims more than the cap; it is refused before any data is read
+        let bomb = gzip_tar(&[raw_entry("demo-1.0.0/src/lib.rs", tar::EntryType::Regular, MAX_EXTRACTED_BYTES + 1, "")]);
+        assert!(unpack_error(&bomb).contains("Archive expands to more than 256 MiB"));
+    }
+
+    #[test]
+    fn rejects_too_many_entries() {
+        let entries: Vec<_> = (0..=MAX_ENTRIES)
+            .map(|i| raw_entry(&format!("demo-1.0.0/f{}", i), tar::EntryType::Regular, 0, ""))
+            .collect();
+        assert!(unpack_error(&gzip_tar(&entries)).contains("more than 50000 entries"));
+    }
+
+    #[test]
+    fn well_formed_archive_unpacks() {
+        let download = unpack_to_temp(&archive(&[("demo-1.0.0/src/lib.rs", "pub fn f() {}\n")])[..], "demo").unwrap();
+        let lib = download.path().join("demo-1.0.0/src/lib.rs");
+        assert_eq!(std::fs::read_to_string(lib).unwrap(), "pub fn f() {}\n");
+    }
 }
@@ -0,0 +1,109 @@
+use crate::audit;
+use crate::config::Config;
+use crate::rules::normalize_code;
+use crate::scanner::Scanner;
+use crate::types::Vulnerability;
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+
+/// File name reported for findings that only exist after macro expansion
+pub const EXPANDED_FILE: &str = "<cargo expand>";
+
+/// Expanded source of the crate at `path`, via the `cargo-expand` plugin
+/// (which itself needs a nightly toolchain)
+pub fn cargo_expand(path: &Path) -> Result<String> {
+    let output = Command::new("cargo")
+        .arg("expand")
+        .arg("--color=never")
+        .current_dir(path)
+        .output()
+        .context("Failed to run `cargo expand`")?;
+
+    if !output.status.success() {
+        bail!(
+            "`cargo expand` failed (is cargo-expand installed?): {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).context("`cargo expand` printed invalid UTF-8")
+}
+
+/// Findings in the expanded source that the pre-expansion scan did not
+/// already report, i.e. panics generated by macros. They are matched back by
+/// rule and normalized code, so their `line` refers to the expanded output.
+pub fn scan_expanded(
+    expanded: String,
+    crate_name: &str,
+    config: &Config,
+    known: &[Vulnerability],
+) -> Vec<Vulnerability> {
+    let seen: HashSet<(String, String)> = known
+        .iter()
+        .map(|v| (v.rule_id.clone(), normalize_code(&v.code)))
+        .collect();
+
+    let mut scanner = Scanner::new(crate_name.to_string(), config.clone());
+    scanner.current_file = EXPANDED_FILE.to_string();
+    // Already warned about; an unparseable expansion just adds nothing
+    let _ = audit::scan_source(&mut scanner, expanded);
+
+    scanner
+        .vulnerabilities
+        .into_iter()
+        .filter(|v| !seen.contains(&(v.rule_id.clone(), normalize_code(&v.code))))
+        .map(|mut v| {
+            v.from_macro = true;
+            v
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+        #[tokio::main]
+        async fn main() {
+            let text = std::fs::read_to_string("data.txt").unwrap();
+            println!("{}", text);
+        }
+    "#;
+
+    /// What `cargo expand` prints for `SOURCE`
+    const EXPANDED: &str = r#"
+        fn main() {
+            let body = async {
+                let text = std::fs::read_to_string("data.txt").unwrap();
+                println!("{}", text);
+            };
+            #[allow(clippy::expect_used, clippy::diverging_sub_expression)]
+            {
+                return tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .expect("Failed building the Runtime")
+                    .block_on(body);
+            }
+        }
+    "#;
+
+    #[test]
+    fn reports_only_macro_generated_panics() {
+        let config = Config::default();
+        let mut scanner = Scanner::new("demo".to_string(), config.clone());
+        scanner.current_file = "src/main.rs".to_string();
+        audit::scan_source(&mut scanner, SOURCE.to_string()).expect("fixture parses");
+        let known = scanner.vulnerabilities;
+        assert!(known.iter().any(|v| v.code.contains("read_to_string")));
+
+        let generated = scan_expanded(EXPANDED.to_string(), "demo", &config, &known);
+        assert_eq!(generated.len(), 1, "{:#?}", generated);
+        assert!(generated[0].code.contains("Failed building the Runtime"), "{}", generated[0].code);
+        assert_eq!(generated[0].file, EXPANDED_FILE);
+        assert!(generated[0].from_macro);
+    }
+}
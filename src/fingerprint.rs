@@ -0,0 +1,46 @@
+use crate::rules::normalize_code;
+use crate::types::Vulnerability;
+use sha2::{Digest, Sha256};
+
+/// Stable id of a finding across runs and tools: a hash of rule, file and
+/// whitespace-normalized code. The line number is left out on purpose so
+/// unrelated edits above a finding don't change its id. Set on every finding
+/// as `Vulnerability::id`; baselines, diffs, SARIF and Code Climate all key
+/// on it.
+pub fn finding_id(vuln: &Vulnerability) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(vuln.rule_id.as_bytes());
+    hasher.update([0]);
+    hasher.update(vuln.file.as_bytes());
+    hasher.update([0]);
+    hasher.update(normalize_code(&vuln.code).as_bytes());
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PanicClass, Severity};
+
+    fn finding(line: usize, code: &str) -> Vulnerability {
+        let file = "src/lib.rs".to_string();
+        Vulnerability::new(file, line, "PA001", Severity::Low, PanicClass::AssumptionPanic, "General Unwrap".to_string(), code.to_string())
+    }
+
+    #[test]
+    fn id_ignores_line_and_whitespace_but_not_code() {
+        let id = finding_id(&finding(3, "config . port . unwrap ()"));
+        assert_eq!(id.len(), 64);
+        assert_eq!(finding_id(&finding(40, "config . port . unwrap ()")), id);
+        assert_eq!(finding_id(&finding(3, "config.port.unwrap()")), id);
+
+        assert_ne!(finding_id(&finding(3, "config . host . unwrap ()")), id);
+        let other_file = Vulnerability { file: "src/main.rs".to_string(), ..finding(3, "config . port . unwrap ()") };
+        assert_ne!(finding_id(&other_file), id);
+    }
+}
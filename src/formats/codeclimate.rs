@@ -0,0 +1,60 @@
+use crate::types::{Report, Severity};
+use serde_json::{json, Value};
+
+/// Code Climate issues, as consumed by GitLab's code quality widget
+pub fn render(report: &Report) -> String {
+    let issues: Vec<Value> = report
+        .findings
+        .iter()
+        .map(|vuln| {
+            json!({
+                "type": "issue",
+                "check_name": vuln.rule_id,
+                "description": format!("{}: {}", vuln.pattern, vuln.code),
+                "categories": ["Bug Risk"],
+                "severity": severity_name(&vuln.severity),
+                "location": {
+                    "path": vuln.file,
+                    "lines": { "begin": vuln.line },
+                },
+                "fingerprint": vuln.id,
+            })
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&issues).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn severity_name(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "critical",
+        Severity::High => "major",
+        Severity::Medium => "minor",
+        Severity::Low => "info",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issues_carry_the_required_code_climate_fields() {
+        let report = crate::formats::sample_report();
+        let issues: Vec<Value> = serde_json::from_str(&render(&report)).expect("valid JSON");
+        assert_eq!(issues.len(), report.findings.len());
+
+        for (issue, vuln) in issues.iter().zip(&report.findings) {
+            assert_eq!(issue["type"], "issue");
+            assert_eq!(issue["check_name"], vuln.rule_id.as_str());
+            assert!(issue["description"].as_str().is_some_and(|d| !d.is_empty()));
+            assert_eq!(issue["categories"], json!(["Bug Risk"]));
+            assert_eq!(issue["location"]["path"], vuln.file.as_str());
+            assert_eq!(issue["location"]["lines"]["begin"], vuln.line);
+            assert_eq!(issue["fingerprint"], vuln.id.as_str());
+        }
+
+        let severities: Vec<&str> = issues.iter().map(|i| i["severity"].as_str().unwrap()).collect();
+        assert_eq!(severities, ["critical", "major", "minor", "info"]);
+    }
+}
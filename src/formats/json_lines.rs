@@ -0,0 +1,49 @@
+use crate::types::{Position, Report, Severity, SourceSpan, Vulnerability};
+use serde_json::json;
+
+/// One editor diagnostic per line, grouped by file and ordered by position:
+/// 1-based start/end line and column, LSP `DiagnosticSeverity` (1 = error ..
+/// 4 = hint), the pattern as message and the rule id as `code`
+pub fn render(report: &Report) -> String {
+    let mut findings: Vec<_> = report.findings.iter().map(|v| (v, span(v))).collect();
+    findings.sort_by(|(a, a_span), (b, b_span)| {
+        (&a.file, a_span.start.line, a_span.start.column)
+            .cmp(&(&b.file, b_span.start.line, b_span.start.column))
+    });
+
+    let mut out = String::new();
+    for (vuln, span) in findings {
+        let diagnostic = json!({
+            "id": vuln.id,
+            "file": vuln.file,
+            "start": { "line": span.start.line, "column": span.start.column },
+            "end": { "line": span.end.line, "column": span.end.column },
+            "severity": lsp_severity(&vuln.severity),
+            "message": vuln.pattern,
+            "code": vuln.rule_id,
+            "source": "cargo-panic-audit",
+        });
+        out.push_str(&diagnostic.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+/// Findings without a recorded span cover their whole line
+fn span(vuln: &Vulnerability) -> SourceSpan {
+    vuln.span.unwrap_or_else(|| {
+        SourceSpan {
+            start: Position { line: vuln.line, column: vuln.column },
+            end: Position { line: vuln.line + 1, column: 1 },
+        }
+    })
+}
+
+fn lsp_severity(severity: &Severity) -> u8 {
+    match severity {
+        Severity::Critical => 1,
+        Severity::High => 2,
+        Severity::Medium => 3,
+        Severity::Low => 4,
+    }
+}
@@ -0,0 +1,167 @@
+use super::xml_escape;
+use crate::types::{PanicClass, Report};
+use std::fmt::Write;
+
+/// JUnit XML with one `<testsuite>` per panic class, so CI dashboards group
+/// failures by category. Every finding is a failed test case; a class with no
+/// findings gets a single passing case so it still shows up as green.
+pub fn render(report: &Report) -> String {
+    let failures = report.findings.len();
+    let tests: usize = PanicClass::ALL
+        .iter()
+        .map(|class| class_count(report, class).max(1))
+        .sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"cargo-panic-audit\" tests=\"{}\" failures=\"{}\" time=\"0\">",
+        tests, failures
+    );
+
+    for class in PanicClass::ALL.iter() {
+        let count = class_count(report, class);
+        let class_name = format!("{:?}", class);
+
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"0\">",
+            class_name,
+            count.max(1),
+            count
+        );
+        let _ = writeln!(xml, "    <properties>");
+        let _ = writeln!(
+            xml,
+            "      <property name=\"crate\" value=\"{}\"/>",
+            xml_escape(&report.crate_name)
+        );
+        let _ = writeln!(
+            xml,
+            "      <property name=\"version\" value=\"{}\"/>",
+            xml_escape(&report.version)
+        );
+        let _ = writeln!(xml, "    </properties>");
+
+        if count == 0 {
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"no findings\" classname=\"{}\" time=\"0\"/>",
+                class_name
+            );
+        }
+
+        for vuln in report.findings.iter().filter(|v| &v.panic_class == class) {
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"{} {}:{}\" classname=\"{}\" time=\"0\">",
+                vuln.rule_id,
+                xml_escape(&vuln.file),
+                vuln.line,
+                class_name
+            );
+            let _ = writeln!(
+                xml,
+                "      <properties><property name=\"finding_id\" value=\"{}\"/></properties>",
+                vuln.id
+            );
+            let _ = writeln!(
+                xml,
+                "      <failure type=\"{:?}\" message=\"{}\">{}</failure>",
+                vuln.severity,
+                xml_escape(&vuln.pattern),
+                xml_escape(&vuln.code)
+            );
+            let _ = writeln!(xml, "    </testcase>");
+        }
+
+        let _ = writeln!(xml, "  </testsuite>");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn class_count(report: &Report, class: &PanicClass) -> usize {
+    report.findings.iter().filter(|v| &v.panic_class == class).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Default)]
+    struct Suite {
+        tests: usize,
+        failures: usize,
+        testcases: usize,
+        failure_elements: usize,
+    }
+
+    fn attribute(element: &quick_xml::events::BytesStart, name: &str) -> String {
+        let attr = element
+            .try_get_attribute(name)
+            .expect("valid attribute")
+            .unwrap_or_else(|| panic!("missing {} attribute", name));
+        attr.unescape_value().expect("valid escape").into_owned()
+    }
+
+    /// Parse the document (failing on malformed XML) into per-suite counts
+    fn suites(xml: &str) -> (usize, usize, HashMap<String, Suite>) {
+        let mut reader = Reader::from_str(xml);
+        let mut totals = (0, 0);
+        let mut suites = HashMap::new();
+        let mut current = String::new();
+        loop {
+            match reader.read_event().expect("well-formed XML") {
+                Event::Start(element) | Event::Empty(element) => match element.name().as_ref() {
+                    b"testsuites" => {
+                        totals = (
+                            attribute(&element, "tests").parse().expect("number"),
+                            attribute(&element, "failures").parse().expect("number"),
+                        );
+                    }
+                    b"testsuite" => {
+                        current = attribute(&element, "name");
+                        let suite = Suite {
+                            tests: attribute(&element, "tests").parse().expect("number"),
+                            failures: attribute(&element, "failures").parse().expect("number"),
+                            ..Suite::default()
+                        };
+                        suites.insert(current.clone(), suite);
+                    }
+                    b"testcase" => suites.get_mut(&current).expect("inside a suite").testcases += 1,
+                    b"failure" => suites.get_mut(&current).expect("inside a suite").failure_elements += 1,
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        (totals.0, totals.1, suites)
+    }
+
+    #[test]
+    fn one_suite_per_class_with_matching_counts() {
+        let report = crate::formats::sample_report();
+        let (tests, failures, suites) = suites(&render(&report));
+
+        assert_eq!(suites.len(), PanicClass::ALL.len());
+        assert_eq!(failures, report.findings.len());
+        assert_eq!(tests, suites.values().map(|suite| suite.tests).sum::<usize>());
+
+        for suite in suites.values() {
+            assert_eq!(suite.tests, suite.testcases);
+            assert_eq!(suite.failures, suite.failure_elements);
+        }
+        assert_eq!(suites["AssumptionPanic"].failures, 2);
+        assert_eq!(suites["CloudflareClass"].failures, 1);
+        assert_eq!(suites["ImplicitPanic"].failures, 1);
+        // Classes without findings still show up, as one passing case
+        assert_eq!(suites["ProcessKilling"].tests, 1);
+        assert_eq!(suites["ProcessKilling"].failures, 0);
+    }
+}
@@ -0,0 +1,93 @@
+//! Machine-readable report formats. Each renderer returns the full document
+//! as a `String` so callers decide where it goes.
+
+pub mod codeclimate;
+pub mod json_lines;
+pub mod junit;
+pub mod sarif;
+pub mod sonarqube;
+pub mod step_summary;
+pub mod xml;
+
+/// Escape text for use in XML attributes and element content
+pub fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A small report with one finding per severity, shared by the format tests
+#[cfg(test)]
+pub fn sample_report() -> crate::types::Report {
+    use crate::types::{PanicClass, Position, Report, Severity, SourceSpan, Vulnerability};
+
+    let finding = |file: &str, line: usize, rule_id: &str, severity, class, pattern: &str, code: &str| {
+        let column = 5;
+        let width = code.chars().count();
+        Vulnerability {
+            column,
+            span: Some(SourceSpan {
+                start: Position { line, column },
+                end: Position { line, column: column + width },
+            }),
+            ..Vulnerability::new(file.to_string(), line, rule_id, severity, class, pattern.to_string(), code.to_string())
+        }
+    };
+
+    Report {
+        crate_name: "demo".to_string(),
+        version: "1.2.3".to_string(),
+        would_fail: true,
+        run: Default::default(),
+        findings: vec![
+            finding(
+                "src/config.rs",
+                12,
+                "PA001",
+                Severity::Critical,
+                PanicClass::CloudflareClass,
+                "Config/Feature File Loading (Cloudflare Pattern)",
+                "fs::read_to_string(\"config.toml\").unwrap()",
+            ),
+            finding(
+                "src/handler.rs",
+                40,
+                "PA002",
+                Severity::High,
+                PanicClass::AssumptionPanic,
+                "Parsing Operation",
+                "body.parse::<u32>().expect(\"number\")",
+            ),
+            finding(
+                "src/handler.rs",
+                52,
+                "PA006",
+                Severity::Medium,
+                PanicClass::ImplicitPanic,
+                "Array/Slice Indexing",
+                "items[a < b && c > d]",
+            ),
+            finding(
+                "src/util.rs",
+                7,
+                "PA001",
+                Severity::Low,
+                PanicClass::AssumptionPanic,
+                "General Unwrap",
+                "name.first().unwrap()",
+            ),
+        ],
+        suppressed: 0,
+        suppressed_findings: Vec::new(),
+        baseline_delta: None,
+    }
+}
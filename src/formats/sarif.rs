@@ -0,0 +1,177 @@
+use crate::cli;
+use crate::rules;
+use crate::types::{PanicClass, Report, Severity, Vulnerability};
+use serde_json::{json, Value};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// SARIF 2.1.0 log for GitHub code scanning (`github/codeql-action/upload-sarif`)
+pub fn render(report: &Report) -> String {
+    let all_rules = rules::all_rules();
+    let rules: Vec<Value> = all_rules
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id,
+                "name": rule.kind,
+                "shortDescription": { "text": rule.message },
+                "defaultConfiguration": { "level": rule_level(rule.severity) },
+            })
+        })
+        .collect();
+
+    // Suppressed findings stay in the log, so the dashboard shows them as
+    // dismissed instead of silently fixed
+    let suppressed = report.suppressed_findings.iter().map(|vuln| {
+        let mut result = result(vuln, all_rules);
+        result["suppressions"] = json!([{ "kind": "inSource", "status": "accepted" }]);
+        result
+    });
+    let results: Vec<Value> = report
+        .findings
+        .iter()
+        .map(|vuln| result(vuln, all_rules))
+        .chain(suppressed)
+        .collect();
+
+    let log = json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "cargo-panic-audit",
+                    "version": cli::VERSION,
+                    "informationUri": "https://github.com/vasusrini/cargo-panic-audit",
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    });
+
+    serde_json::to_string_pretty(&log).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn result(vuln: &Vulnerability, all_rules: &[rules::Rule]) -> Value {
+    let mut region = json!({ "startLine": vuln.line, "startColumn": vuln.column });
+    if let Some(span) = vuln.span {
+        region["endLine"] = json!(span.end.line);
+        region["endColumn"] = json!(span.end.column);
+    }
+
+    let mut result = json!({
+        "ruleId": vuln.rule_id,
+        "level": level(&vuln.severity),
+        "rank": rank(vuln),
+        "message": { "text": format!("{}: {}", vuln.pattern, vuln.code) },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": vuln.file },
+                "region": region,
+            },
+        }],
+        "partialFingerprints": { "panicAudit/v1": vuln.id },
+        "properties": {
+            "severity": format!("{:?}", vuln.severity),
+            "panicClass": format!("{:?}", vuln.panic_class),
+        },
+    });
+    if let Some(index) = all_rules.iter().position(|rule| rule.id == vuln.rule_id) {
+        result["ruleIndex"] = json!(index);
+    }
+    result
+}
+
+/// SARIF priority (0-100) for dashboard sorting: the severity band, nudged
+/// up for untrusted input and hot paths so ties order by blast radius
+fn rank(vuln: &Vulnerability) -> f64 {
+    let band: f64 = match vuln.severity {
+        Severity::Critical => 90.0,
+        Severity::High => 70.0,
+        Severity::Medium => 40.0,
+        Severity::Low => 10.0,
+    };
+    let untrusted = if vuln.panic_class == PanicClass::CloudflareClass { 5.0 } else { 0.0 };
+    let hot = if vuln.hot_path { 5.0 } else { 0.0 };
+    (band + untrusted + hot).min(100.0)
+}
+
+/// Critical and High fail code scanning checks; Medium and Low annotate
+fn level(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Critical | Severity::High => "error",
+        Severity::Medium => "warning",
+        Severity::Low => "note",
+    }
+}
+
+/// Same mapping for a rule's default severity (`"CRITICAL"`, `"MEDIUM"`, ...)
+fn rule_level(severity: &str) -> &'static str {
+    match severity {
+        "CRITICAL" | "HIGH" => "error",
+        "MEDIUM" => "warning",
+        _ => "note",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(report: &Report) -> Vec<Value> {
+        let log: Value = serde_json::from_str(&render(report)).expect("valid JSON");
+        log["runs"][0]["results"].as_array().expect("results").clone()
+    }
+
+    #[test]
+    fn results_carry_level_rule_and_location() {
+        let report = crate::formats::sample_report();
+        let log: Value = serde_json::from_str(&render(&report)).expect("valid JSON");
+        assert_eq!(log["version"], "2.1.0");
+
+        let driver_rules = log["runs"][0]["tool"]["driver"]["rules"].as_array().expect("rules");
+        assert_eq!(driver_rules.len(), rules::all_rules().len());
+
+        let results = results(&report);
+        let levels: Vec<&str> = results
+            .iter()
+            .map(|result| result["level"].as_str().expect("level"))
+            .collect();
+        assert_eq!(levels, ["error", "error", "warning", "note"]);
+
+        let first = &results[0];
+        assert_eq!(first["ruleId"], "PA001");
+        assert_eq!(driver_rules[first["ruleIndex"].as_u64().expect("rule index") as usize]["id"], "PA001");
+        let location = &first["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/config.rs");
+        assert_eq!(location["region"]["startLine"], 12);
+        assert_eq!(location["region"]["startColumn"], 5);
+    }
+
+    #[test]
+    fn rank_orders_results_by_severity() {
+        let ranks: Vec<f64> = results(&crate::formats::sample_report())
+            .iter()
+            .map(|result| result["rank"].as_f64().expect("rank"))
+            .collect();
+        assert!(ranks.iter().all(|rank| (0.0..=100.0).contains(rank)));
+        assert!(ranks.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", ranks);
+    }
+
+    #[test]
+    fn suppressed_finding_appears_with_suppressions() {
+        let mut report = crate::formats::sample_report();
+        let silenced = report.findings.pop().expect("a finding");
+        report.suppressed = 1;
+        report.suppressed_findings = vec![silenced.clone()];
+
+        let results = results(&report);
+        assert_eq!(results.len(), report.findings.len() + 1);
+        assert!(results[..report.findings.len()].iter().all(|result| result.get("suppressions").is_none()));
+
+        let dismissed = results.last().expect("suppressed result");
+        assert_eq!(dismissed["partialFingerprints"]["panicAudit/v1"], silenced.id);
+        assert_eq!(dismissed["suppressions"], json!([{ "kind": "inSource", "status": "accepted" }]));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::types::{Report, Severity};
+use serde_json::{json, Value};
+
+/// SonarQube generic issue import (`sonar.externalIssuesReportPaths`)
+pub fn render(report: &Report) -> String {
+    let issues: Vec<Value> = report
+        .findings
+        .iter()
+        .map(|vuln| {
+            let (start_line, end_line) = vuln
+                .span
+                .map(|span| (span.start.line, span.end.line))
+                .unwrap_or((vuln.line, vuln.line));
+
+            json!({
+                "engineId": "panic-audit",
+                "ruleId": vuln.rule_id,
+                "severity": severity_name(&vuln.severity),
+                "type": "BUG",
+                "primaryLocation": {
+                    // The generic import has no id field; keep it with the message
+                    "message": format!("{}: {} [{}]", vuln.pattern, vuln.code, vuln.id),
+                    "filePath": vuln.file,
+                    "textRange": { "startLine": start_line, "endLine": end_line },
+                },
+            })
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&json!({ "issues": issues }))
+        .unwrap_or_else(|_| "{\"issues\":[]}".to_string())
+}
+
+fn severity_name(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "BLOCKER",
+        Severity::High => "CRITICAL",
+        Severity::Medium => "MAJOR",
+        Severity::Low => "MINOR",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issues_follow_the_generic_import_structure() {
+        let report = crate::formats::sample_report();
+        let document: Value = serde_json::from_str(&render(&report)).expect("valid JSON");
+        let issues = document["issues"].as_array().expect("issues array");
+        assert_eq!(issues.len(), report.findings.len());
+
+        for (issue, vuln) in issues.iter().zip(&report.findings) {
+            assert_eq!(issue["engineId"], "panic-audit");
+            assert_eq!(issue["ruleId"], vuln.rule_id.as_str());
+            assert_eq!(issue["type"], "BUG");
+            let location = &issue["primaryLocation"];
+            assert!(location["message"].as_str().is_some_and(|message| message.contains(&vuln.id)));
+            assert_eq!(location["filePath"], vuln.file.as_str());
+            assert_eq!(location["textRange"], json!({ "startLine": vuln.line, "endLine": vuln.line }));
+        }
+
+        let severities: Vec<&str> = issues.iter().map(|issue| issue["severity"].as_str().unwrap()).collect();
+        assert_eq!(severities, ["BLOCKER", "CRITICAL", "MAJOR", "MINOR"]);
+    }
+}
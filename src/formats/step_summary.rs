@@ -0,0 +1,45 @@
+use crate::remediation;
+use crate::types::{Report, Severity};
+use std::fmt::Write;
+
+/// Compact Markdown for `$GITHUB_STEP_SUMMARY`: a severity table and the
+/// findings worth fixing first, shown on the Actions run page
+pub fn render(report: &Report) -> String {
+    let mut md = String::new();
+    let verdict = if report.would_fail { "❌ failed" } else { "✅ passed" };
+    let _ = writeln!(md, "## Panic audit: {} v{} ({})\n", report.crate_name, report.version, verdict);
+
+    let _ = writeln!(md, "| Severity | Findings |");
+    let _ = writeln!(md, "|---|---:|");
+    for severity in Severity::ALL.iter() {
+        let count = report.findings.iter().filter(|v| v.severity == *severity).count();
+        let _ = writeln!(md, "| {:?} | {} |", severity, count);
+    }
+    let _ = writeln!(md, "| **Total** | **{}** |", report.findings.len());
+
+    if report.findings.is_empty() {
+        return md;
+    }
+
+    let _ = writeln!(md, "\n### What to fix first\n");
+    let _ = writeln!(md, "| Severity | Location | Pattern | Fix |");
+    let _ = writeln!(md, "|---|---|---|---|");
+    for vuln in remediation::fix_first(&report.findings, 5) {
+        let _ = writeln!(
+            md,
+            "| {:?} | `{}:{}` | {} | {} |",
+            vuln.severity,
+            cell(&vuln.file),
+            vuln.line,
+            cell(&vuln.pattern),
+            cell(remediation::remediation(vuln))
+        );
+    }
+
+    md
+}
+
+/// Keep a value from breaking out of its table cell
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
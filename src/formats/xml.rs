@@ -0,0 +1,114 @@
+use crate::types::Report;
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use std::io;
+
+/// Plain findings dump for XSLT-style tooling:
+/// `<findings crate version><finding id severity class rule file line><code>..`
+pub fn render(report: &Report) -> String {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    // Writing into a Vec cannot fail
+    let _ = write_findings(&mut writer, report);
+
+    let mut xml = String::from_utf8_lossy(&writer.into_inner()).into_owned();
+    xml.push('\n');
+    xml
+}
+
+fn write_findings(writer: &mut Writer<Vec<u8>>, report: &Report) -> io::Result<()> {
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("findings")
+        .with_attribute(("crate", report.crate_name.as_str()))
+        .with_attribute(("version", report.version.as_str()))
+        .write_inner_content(|writer| {
+            for vuln in &report.findings {
+                let severity = format!("{:?}", vuln.severity);
+                let class = format!("{:?}", vuln.panic_class);
+                let line = vuln.line.to_string();
+                let column = vuln.column.to_string();
+                writer
+                    .create_element("finding")
+                    .with_attribute(("id", vuln.id.as_str()))
+                    .with_attribute(("severity", severity.as_str()))
+                    .with_attribute(("class", class.as_str()))
+                    .with_attribute(("rule", vuln.rule_id.as_str()))
+                    .with_attribute(("file", vuln.file.as_str()))
+                    .with_attribute(("line", line.as_str()))
+                    .with_attribute(("column", column.as_str()))
+                    .write_inner_content(|writer| {
+                        writer
+                            .create_element("code")
+                            .write_text_content(BytesText::new(&vuln.code))?;
+                        Ok(())
+                    })?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    /// Each `<finding>`'s attributes and unescaped `<code>` text, failing on
+    /// malformed XML
+    fn findings(xml: &str) -> Vec<(Vec<(String, String)>, String)> {
+        let mut reader = Reader::from_str(xml);
+        let mut findings = Vec::new();
+        let mut in_code = false;
+        loop {
+            match reader.read_event().expect("well-formed XML") {
+                Event::Start(element) if element.name().as_ref() == b"finding" => {
+                    let attributes = element
+                        .attributes()
+                        .map(|attr| {
+                            let attr = attr.expect("valid attribute");
+                            let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+                            (key, attr.unescape_value().expect("valid escape").into_owned())
+                        })
+                        .collect();
+                    findings.push((attributes, String::new()));
+                }
+                Event::Start(element) if element.name().as_ref() == b"code" => in_code = true,
+                Event::End(element) if element.name().as_ref() == b"code" => in_code = false,
+                Event::Text(text) if in_code => {
+                    findings.last_mut().expect("code inside a finding").1 += &text.unescape().expect("valid escape");
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        findings
+    }
+
+    #[test]
+    fn parses_back_to_the_findings() {
+        let report = crate::formats::sample_report();
+        let xml = render(&report);
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+
+        let parsed = findings(&xml);
+        assert_eq!(parsed.len(), report.findings.len());
+        for ((attributes, code), vuln) in parsed.iter().zip(&report.findings) {
+            let attribute = |name: &str| attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+            assert_eq!(attribute("id"), Some(vuln.id.as_str()));
+            assert_eq!(attribute("severity"), Some(format!("{:?}", vuln.severity).as_str()));
+            assert_eq!(attribute("file"), Some(vuln.file.as_str()));
+            assert_eq!(attribute("line"), Some(vuln.line.to_string().as_str()));
+            assert_eq!(code, &vuln.code);
+        }
+    }
+
+    #[test]
+    fn escapes_markup_in_code() {
+        let report = crate::formats::sample_report();
+        let xml = render(&report);
+        assert!(xml.contains("<code>items[a &lt; b &amp;&amp; c &gt; d]</code>"), "{}", xml);
+        assert!(xml.contains("<code>body.parse::&lt;u32&gt;().expect(&quot;number&quot;)</code>"), "{}", xml);
+        assert_eq!(findings(&xml)[2].1, "items[a < b && c > d]");
+    }
+}
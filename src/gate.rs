@@ -0,0 +1,162 @@
+use crate::cli::Args;
+use crate::config::PolicyRule;
+use crate::types::{Severity, Vulnerability};
+
+/// Decide whether the run fails, returning one human-readable reason per
+/// failed check (empty means the gate passed). Config policy rules are
+/// checked in addition to the command-line gates.
+pub fn evaluate(findings: &[Vulnerability], args: &Args, policy: &[PolicyRule]) -> Vec<String> {
+    let mut failures = flag_failures(findings, args);
+    failures.extend(policy_failures(findings, policy));
+    failures
+}
+
+/// Budgets are the more precise tool, so when any `--max-*` is given they
+/// replace the boolean `--fail-on-findings`/`--warnings-as-errors` gates
+/// rather than adding to them.
+fn flag_failures(findings: &[Vulnerability], args: &Args) -> Vec<String> {
+    let budgets = [
+        (Severity::Critical, args.max_critical),
+        (Severity::High, args.max_high),
+        (Severity::Medium, args.max_medium),
+        (Severity::Low, args.max_low),
+    ];
+
+    if budgets.iter().any(|(_, max)| max.is_some()) {
+        return budgets
+            .iter()
+            .filter_map(|(severity, max)| {
+                let max = (*max)?;
+                let count = count_severity(findings, severity);
+                (count > max).then(|| {
+                    format!(
+                        "{:?} budget exceeded: {} found, max {} (over by {})",
+                        severity,
+                        count,
+                        max,
+                        count - max
+                    )
+                })
+            })
+            .collect();
+    }
+
+    if args.warnings_as_errors && !findings.is_empty() {
+        return vec![format!("{} finding(s) with --warnings-as-errors", findings.len())];
+    }
+
+    let critical = count_severity(findings, &Severity::Critical);
+    if args.fail_on_findings && critical > 0 {
+        return vec![format!("{} critical finding(s) with --fail-on-findings", critical)];
+    }
+
+    Vec::new()
+}
+
+/// Each finding is judged by the first policy rule that matches it, in
+/// config order; findings no rule matches never fail the run.
+fn policy_failures(findings: &[Vulnerability], policy: &[PolicyRule]) -> Vec<String> {
+    let mut failing: Vec<Vec<&Vulnerability>> = vec![Vec::new(); policy.len()];
+
+    for vuln in findings {
+        let Some((index, rule)) = policy.iter().enumerate().find(|(_, rule)| rule.matches(vuln)) else {
+            continue;
+        };
+        if rule.fail_on.as_ref().is_some_and(|threshold| vuln.severity <= *threshold) {
+            failing[index].push(vuln);
+        }
+    }
+
+    policy
+        .iter()
+        .zip(failing)
+        .enumerate()
+        .filter(|(_, (_, failed))| !failed.is_empty())
+        .filter_map(|(index, (rule, failed))| {
+            let threshold = rule.fail_on.as_ref()?;
+            Some(format!(
+                "Policy rule {} ({}) fails on {:?} or worse: {} finding(s), first at {}:{}",
+                index + 1,
+                rule.describe(),
+                threshold,
+                failed.len(),
+                failed[0].file,
+                failed[0].line
+            ))
+        })
+        .collect()
+}
+
+/// `--exit-code-by-severity`: 10 + the worst severity's rank, 0 when clean
+pub fn severity_exit_code(findings: &[Vulnerability]) -> i32 {
+    match findings.iter().map(|v| &v.severity).min() {
+        Some(Severity::Critical) => 10,
+        Some(Severity::High) => 11,
+        Some(Severity::Medium) => 12,
+        Some(Severity::Low) => 13,
+        None => 0,
+    }
+}
+
+fn count_severity(findings: &[Vulnerability], severity: &Severity) -> usize {
+    findings.iter().filter(|v| &v.severity == severity).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::types::PanicClass;
+
+    fn finding(file: &str, severity: Severity) -> Vulnerability {
+        let code = "value.unwrap()".to_string();
+        Vulnerability::new(file.to_string(), 1, "PA001", severity, PanicClass::AssumptionPanic, "General Unwrap".to_string(), code)
+    }
+
+    fn policy(toml: &str) -> Vec<PolicyRule> {
+        toml::from_str::<Config>(toml).expect("valid config").policy
+    }
+
+    #[test]
+    fn path_policy_fails_highs_only_under_its_path() {
+        let policy = policy(
+            r#"
+            [[policy]]
+            path = "src/net/**"
+            fail_on = "High"
+
+            [[policy]]
+            fail_on = "Critical"
+            "#,
+        );
+
+        let elsewhere = [finding("src/cli.rs", Severity::High), finding("src/net.rs", Severity::High)];
+        assert!(policy_failures(&elsewhere, &policy).is_empty());
+
+        let networking = [finding("src/cli.rs", Severity::High), finding("src/net/tcp.rs", Severity::High)];
+        let failures = policy_failures(&networking, &policy);
+        assert_eq!(failures.len(), 1, "{:?}", failures);
+        assert!(failures[0].starts_with("Policy rule 1 (path src/net/**)"), "{}", failures[0]);
+        assert!(failures[0].ends_with("first at src/net/tcp.rs:1"), "{}", failures[0]);
+
+        let critical = [finding("src/cli.rs", Severity::Critical)];
+        assert_eq!(policy_failures(&critical, &policy).len(), 1);
+    }
+
+    #[test]
+    fn first_matching_policy_rule_decides() {
+        // The path rule exempts src/generated, so the catch-all never sees it
+        let policy = policy(
+            r#"
+            [[policy]]
+            path = "src/generated/**"
+
+            [[policy]]
+            fail_on = "Medium"
+            "#,
+        );
+
+        assert!(policy_failures(&[finding("src/generated/api.rs", Severity::Critical)], &policy).is_empty());
+        assert_eq!(policy_failures(&[finding("src/lib.rs", Severity::Medium)], &policy).len(), 1);
+    }
+}
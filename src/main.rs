@@ -1,17 +1,41 @@
 mod audit;
+mod baseline;
 mod cli;
+mod config;
+mod deps;
+mod diff;
 mod download;
+mod expand;
+mod fingerprint;
+mod formats;
+mod gate;
+mod manifest;
+mod remediation;
 mod report;
 mod rules;
 mod scanner;
 mod types;
 
-use anyhow::Result;
-use std::fs;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing_subscriber::EnvFilter;
 
 fn main() -> Result<()> {
+    let started_at = std::time::SystemTime::now();
     let args = cli::parse();
+    init_logging(args.log_level.as_deref());
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .context("Failed to start the scan thread pool")?;
+    }
+
+    match args.color {
+        cli::ColorChoice::Auto => {}
+        cli::ColorChoice::Always => colored::control::set_override(true),
+        cli::ColorChoice::Never => colored::control::set_override(false),
+    }
 
     // Handle legend display
     if args.legend {
@@ -19,68 +43,429 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    report::print_banner();
-    report::print_what_we_detect(args.explain);
+    if let Some(cli::Command::Render { report }) = &args.command {
+        return render_saved_report(report, &args);
+    }
+
+    let mut config = config::Config::load(args.config.as_deref())?;
+    let mut other_config = args
+        .compare_rules
+        .as_deref()
+        .map(|path| config::Config::load(Some(path)))
+        .transpose()?;
+    for config in std::iter::once(&mut config).chain(other_config.as_mut()) {
+        config.include_tests |= args.include_tests;
+        config.trust_unsafe_invariants |= args.trust_unsafe_invariants;
+        config.follow_symlinks |= args.follow_symlinks;
+        config.exploitability.enabled |= args.rule_severity_from_exploitability;
+        if args.max_depth.is_some() {
+            config.max_depth = args.max_depth;
+        }
+    }
 
-    let (scan_path, crate_name, version, cleanup_needed) = if args.local {
+    if args.config_print_effective {
+        return print_effective_config(&config, &args);
+    }
+
+    if let Some(lockfile) = &args.deps {
+        return audit_dependencies(lockfile, &config, &args, started_at);
+    }
+
+    let Some(target) = args.crate_name.clone() else {
+        anyhow::bail!("Missing crate name or path to scan");
+    };
+
+    if args.compare_with_crates_io {
+        if !args.local {
+            anyhow::bail!("--compare-with-crates-io compares a local crate; pass its path with --local");
+        }
+        if !matches!(args.output_format(), cli::OutputFormat::Human | cli::OutputFormat::Json) {
+            anyhow::bail!("--compare-with-crates-io supports human and JSON output only");
+        }
+    }
+
+    if other_config.is_some() &&
+        !matches!(args.output_format(), cli::OutputFormat::Human | cli::OutputFormat::Json) {
+        anyhow::bail!("--compare-rules supports human and JSON output only");
+    }
+
+    // Keep stdout clean for machine-readable output
+    if !args.is_machine_output() {
+        report::print_banner();
+        report::print_what_we_detect(args.explain);
+    }
+
+    // `download` owns downloaded sources; temporary ones are deleted when dropped
+    let (scan_path, crate_name, version, download) = if args.local {
         // Scan local path
-        let path = PathBuf::from(&args.crate_name);
+        let path = PathBuf::from(&target);
         if !path.exists() {
-            anyhow::bail!("Path does not exist: {}", args.crate_name);
+            anyhow::bail!("Path does not exist: {}", target);
         }
         
-        let crate_name = path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or(&args.crate_name)
-            .to_string();
+        let crate_name = match &args.crate_root {
+            Some(root) => diff::read_package_name(root)?,
+            None => path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or(&target)
+                .to_string(),
+        };
         
-        println!("\n📂 Scanning local path: {}", path.display());
-        (path, crate_name, "local".to_string(), false)
+        eprintln!("\n📂 Scanning local path: {}", path.display());
+        (path, crate_name, "local".to_string(), None)
+    } else if target.ends_with(".crate") && Path::new(&target).is_file() {
+        // A `.crate` archive on disk: same unpacking, no network
+        let path = Path::new(&target);
+        let (crate_name, version) = download::split_crate_file_name(path)
+            .with_context(|| format!("Expected a <name>-<version>.crate file name: {}", target))?;
+        eprintln!();
+        let download = download::unpack_local_crate(path)?;
+        (download.path().to_path_buf(), crate_name, version, Some(download))
     } else {
         // Download from crates.io
-        let crate_name = &args.crate_name;
+        let crate_name = &target;
         
         let version = if let Some(v) = args.version.clone() {
             v
         } else {
-            println!("\n🔎 Finding latest version...");
+            eprintln!("\n🔎 Finding latest version...");
             download::get_latest_version(crate_name)?
         };
 
-        println!();
-        let temp_dir = download::download_crate(crate_name, &version)?;
-        (temp_dir, crate_name.clone(), version, true)
+        eprintln!();
+        let download = download::download_crate(crate_name, &version, &args.download_options())?;
+        (download.path().to_path_buf(), crate_name.clone(), version, Some(download))
     };
 
-    let mut vulnerabilities = audit::scan_directory(&scan_path, &crate_name);
+    let scan = audit::scan_directory(&scan_path, &crate_name, &config);
+    let mut vulnerabilities = scan.findings;
+    let crate_root = args.crate_root.clone().unwrap_or_else(|| scan_path.clone());
 
-    if cleanup_needed {
-        println!("\n🧹 Cleaning up...");
-        fs::remove_dir_all(&scan_path)?;
+    if args.audit_macros_expanded {
+        if !args.local {
+            eprintln!("⚠️  --audit-macros-expanded needs a local crate with its dependencies; skipping");
+        } else {
+            eprintln!("🧩 Expanding macros with `cargo expand`...");
+            match expand::cargo_expand(&crate_root) {
+                Ok(expanded) => {
+                    let generated = expand::scan_expanded(expanded, &crate_name, &config, &vulnerabilities);
+                    eprintln!("   {} additional findings in macro-generated code", generated.len());
+                    vulnerabilities.extend(generated);
+                    audit::sort_findings(&mut vulnerabilities);
+                }
+                Err(e) => eprintln!("⚠️  Skipping macro expansion: {:#}", e),
+            }
+        }
     }
 
-    report::print_report(&mut vulnerabilities, &crate_name, &version, &args);
+    if let Some(other) = &other_config {
+        let comparison = diff::compare_rules(&scan_path, &crate_name, vulnerabilities, &config, other);
+        // Gates judge what the current rules add over the other config
+        let gate_failures = gate::evaluate(&comparison.only_current, &args, &config.policy);
+        report::print_rule_comparison(&comparison, &args);
+        if let Some(download) = download {
+            download.close()?;
+        }
 
-    println!("\n{}", "═".repeat(80));
+        for failure in &gate_failures {
+            eprintln!("gate failed (findings only the current rules produce): {}", failure);
+        }
+        exit_if_unparsed(&scan.unparsed, &args);
+        if args.exit_code_by_severity {
+            std::process::exit(gate::severity_exit_code(&comparison.only_current));
+        }
+        if !gate_failures.is_empty() && !args.no_exit {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.compare_with_crates_io {
+        let comparison = diff::compare_with_published(&crate_root, &scan_path, vulnerabilities, &config, &args.download_options())?;
+        let gate_failures = gate::evaluate(&comparison.new, &args, &config.policy);
+        report::print_comparison(&comparison, &args);
+        if args.is_machine_output() {
+            eprintln!("{}", comparison.baseline_delta.summary());
+        }
+
+        for failure in &gate_failures {
+            eprintln!("gate failed (new findings): {}", failure);
+        }
+        exit_if_unparsed(&scan.unparsed, &args);
+        if args.exit_code_by_severity {
+            std::process::exit(gate::severity_exit_code(&comparison.new));
+        }
+        if !gate_failures.is_empty() && !args.no_exit {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Comparisons rescan the other side unfiltered, so this only shapes the report
+    if args.min_code_length.is_some() || args.drop_unresolved {
+        let dropped = audit::drop_low_quality(
+            &mut vulnerabilities,
+            args.min_code_length.unwrap_or(0),
+            args.drop_unresolved,
+        );
+        eprintln!("   Dropped {} finding(s) with short or unlocatable code", dropped);
+    }
+
+    let mut baseline_delta = None;
+    if let Some(path) = &args.baseline {
+        let accepted = if args.write_baseline {
+            let accepted = baseline::Baseline::from_findings(&vulnerabilities);
+            accepted.write(path)?;
+            eprintln!("📌 Wrote baseline of {} finding(s) to {}", accepted.entries.len(), path.display());
+            accepted
+        } else {
+            baseline::Baseline::load(path)?
+        };
+        let filtered = accepted.filter(vulnerabilities);
+        eprintln!(
+            "   {} finding(s) in baseline, {} new; {} stale baseline entries",
+            filtered.known,
+            filtered.new.len(),
+            filtered.stale
+        );
+        let delta = filtered.delta();
+        eprintln!("   {}", delta.summary());
+        baseline_delta = Some(delta);
+        vulnerabilities = filtered.new;
+    }
 
     let has_critical = vulnerabilities
         .iter()
         .any(|v| matches!(v.severity, types::Severity::Critical));
-    
-    if has_critical && args.fail_on_findings {
-        println!("\n{}", "⚠️  CRITICAL: This crate contains patterns that can take down production!".to_string().as_str());
-        println!("{}", "    Review and fix critical issues before deploying.");
-        std::process::exit(1);
+    let gate_failures = gate::evaluate(&vulnerabilities, &args, &config.policy);
+    let would_fail = !gate_failures.is_empty();
+
+    let source = if args.local {
+        manifest::ScanSource { kind: "local".to_string(), id: scan_path.display().to_string() }
+    } else if target.ends_with(".crate") {
+        manifest::ScanSource { kind: "crate-file".to_string(), id: target.clone() }
     } else {
-        println!("\n{}", "✅ Audit complete!");
-        if vulnerabilities.is_empty() {
-            println!("{}", "   No panic patterns detected.");
-        } else if has_critical {
-            println!("{}", "   ⚠️  Critical issues found - review before production deployment.");
+        manifest::ScanSource { kind: "crates.io".to_string(), id: format!("{}@{}", crate_name, version) }
+    };
+
+    let mut report = types::Report {
+        crate_name,
+        version,
+        would_fail,
+        run: manifest::RunManifest::new(&args, &config, source, started_at),
+        findings: if args.dedupe_across_files {
+            audit::dedupe_across_files(vulnerabilities)
         } else {
-            println!("{}", "   No critical issues found, but review high/medium patterns.");
+            vulnerabilities
+        },
+        suppressed: scan.suppressed.len(),
+        suppressed_findings: scan.suppressed,
+        baseline_delta,
+    };
+
+    report::print_report(&mut report, &args);
+    if args.pretty_context && !args.is_machine_output() {
+        report::print_pretty_context(&report.findings, &scan_path);
+    }
+
+    if let Some(download) = download {
+        download.close()?;
+    }
+
+    if !args.is_machine_output() {
+        print_verdict(&report, &gate_failures, has_critical, args.no_exit);
+    } else {
+        for failure in &gate_failures {
+            eprintln!("gate failed: {}", failure);
         }
     }
 
+    exit_if_unparsed(&scan.unparsed, &args);
+    if args.exit_code_by_severity {
+        // Gates can only fail on findings, so a failed gate is never 0 here
+        std::process::exit(gate::severity_exit_code(&report.findings));
+    }
+    if would_fail && !args.no_exit {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// `--strict-parse`: list every file that wasn't audited and exit 2
+fn exit_if_unparsed(unparsed: &[audit::ParseFailure], args: &cli::Args) {
+    if !args.strict_parse || unparsed.is_empty() {
+        return;
+    }
+    eprintln!("strict parse failed: {} file(s) not audited", unparsed.len());
+    for failure in unparsed {
+        eprintln!("  {}: {}", failure.file, failure.error);
+    }
+    std::process::exit(2);
+}
+
+/// `--deps`: scan every crates.io dependency in a lockfile; gates apply to
+/// the findings of all of them together
+fn audit_dependencies(
+    lockfile: &std::path::Path,
+    config: &config::Config,
+    args: &cli::Args,
+    started_at: std::time::SystemTime,
+) -> Result<()> {
+    let format = args.output_format();
+    if args.output_dir.is_some() {
+        // The format is for the files; stdout keeps the grouped report
+        if format == cli::OutputFormat::Human {
+            anyhow::bail!("--output-dir needs a report format for its files, e.g. --format sarif or --json");
+        }
+    } else if !matches!(format, cli::OutputFormat::Human | cli::OutputFormat::Json) {
+        anyhow::bail!("--deps supports human and JSON output only; use --output-dir for per-crate files in other formats");
+    }
+    if !args.is_machine_output() {
+        report::print_banner();
+    }
+
+    let source = deps::CratesIo(args.download_options());
+    let fails_gate = |findings: &[types::Vulnerability]| !gate::evaluate(findings, args, &config.policy).is_empty();
+    let options = deps::DepsOptions {
+        since: args.since,
+        fail_fast: args.fail_fast.then_some(&fails_gate as deps::GateCheck),
+    };
+    let deps = deps::audit_lockfile(lockfile, config, &source, &options)?;
+    report::print_deps_report(&deps, args);
+
+    if let Some(dir) = &args.output_dir {
+        let reports: Vec<types::Report> = deps
+            .crates
+            .iter()
+            .map(|krate| {
+                let id = format!("{}@{}", krate.crate_name, krate.version);
+                let source = manifest::ScanSource { kind: "crates.io".to_string(), id };
+                types::Report {
+                    crate_name: krate.crate_name.clone(),
+                    version: krate.version.clone(),
+                    would_fail: fails_gate(&krate.findings),
+                    run: manifest::RunManifest::new(args, config, source, started_at),
+                    findings: krate.findings.clone(),
+                    suppressed: 0,
+                    suppressed_findings: Vec::new(),
+                    baseline_delta: None,
+                }
+            })
+            .collect();
+        let index = report::write_deps_reports(&deps, &reports, dir, format)?;
+        eprintln!("📁 Wrote {} report(s) and {}", reports.len(), index.display());
+    }
+
+    let findings = deps.all_findings();
+    let gate_failures = gate::evaluate(&findings, args, &config.policy);
+    for failure in &gate_failures {
+        eprintln!("gate failed: {}", failure);
+    }
+    exit_if_unparsed(&deps.unparsed, args);
+    if args.exit_code_by_severity {
+        std::process::exit(gate::severity_exit_code(&findings));
+    }
+    if !gate_failures.is_empty() && !args.no_exit {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `--config-print-effective`: the config after command-line flags were
+/// applied on top of the file (or the defaults, without one)
+fn print_effective_config(config: &config::Config, args: &cli::Args) -> Result<()> {
+    if args.output_format() == cli::OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(config)?);
+        return Ok(());
+    }
+
+    let source = match &config.source {
+        Some(path) => path.display().to_string(),
+        None => "no config file".to_string(),
+    };
+    println!("# Effective configuration: defaults < {} < command-line flags", source);
+    print!("{}", toml::to_string(config)?);
     Ok(())
 }
+
+/// `render`: load a `--json` report and print it as if it had just been scanned
+fn render_saved_report(path: &std::path::Path, args: &cli::Args) -> Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read report {}", path.display()))?;
+    let mut report: types::Report = serde_json::from_str(&text)
+        .with_context(|| format!("Not a cargo-panic-audit JSON report: {}", path.display()))?;
+    // Reports from before finding ids get them, so every format can show one
+    let findings = report.findings.iter_mut().chain(&mut report.suppressed_findings);
+    for vuln in findings.filter(|vuln| vuln.id.is_empty()) {
+        vuln.id = fingerprint::finding_id(vuln);
+    }
+
+    report::print_report(&mut report, args);
+    if args.pretty_context && !args.is_machine_output() {
+        // Sources are only around for local scans, at the path that was scanned
+        if report.run.source.kind == "local" {
+            report::print_pretty_context(&report.findings, std::path::Path::new(&report.run.source.id));
+        } else {
+            eprintln!("⚠️  --pretty-context needs the scanned sources; only local reports can show them");
+        }
+    }
+
+    if !args.is_machine_output() {
+        let has_critical = report
+            .findings
+            .iter()
+            .any(|v| matches!(v.severity, types::Severity::Critical));
+        print_verdict(&report, &[], has_critical, false);
+    }
+
+    Ok(())
+}
+
+/// Diagnostics are opt-in: `--log-level` wins over `RUST_LOG`, otherwise off
+fn init_logging(level: Option<&str>) {
+    let filter = match level {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("off")),
+    };
+
+    // Plain text when stderr is redirected to a file or a bug report
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+        .init();
+}
+
+fn print_verdict(
+    report: &types::Report,
+    gate_failures: &[String],
+    has_critical: bool,
+    no_exit: bool,
+) {
+    println!("\n{}", "═".repeat(80));
+
+    if report.would_fail {
+        if has_critical {
+            println!("\n⚠️  CRITICAL: This crate contains patterns that can take down production!");
+        } else {
+            println!("\n⚠️  Audit gate failed!");
+        }
+        for failure in gate_failures {
+            println!("    • {}", failure);
+        }
+        println!("    Review and fix critical issues before deploying.");
+        if no_exit {
+            println!("    (--no-exit: exiting with status 0 anyway)");
+        }
+    } else {
+        println!("\n✅ Audit complete!");
+        if report.findings.is_empty() {
+            println!("   No panic patterns detected.");
+        } else if has_critical {
+            println!("   ⚠️  Critical issues found - review before production deployment.");
+        } else {
+            println!("   No critical issues found, but review high/medium patterns.");
+        }
+    }
+}
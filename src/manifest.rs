@@ -0,0 +1,132 @@
+use crate::cli::{self, Args};
+use crate::config::{Config, Exploitability};
+use crate::rules;
+use crate::types::Severity;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+/// Parameters of one run, recorded in the JSON report so it can be
+/// reproduced and compared with other reports
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub tool_version: String,
+    /// Hash of the rule table and tool version; changes whenever rules may
+    /// classify differently
+    pub ruleset_hash: String,
+    /// Config file that was loaded, if any
+    pub config_path: Option<String>,
+    pub source: ScanSource,
+    pub options: RunOptions,
+    /// RFC 3339, UTC
+    pub started_at: String,
+}
+
+/// What was scanned: `local` with a path, or `crates.io` with `name@version`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanSource {
+    pub kind: String,
+    pub id: String,
+}
+
+/// Flags and config settings that affect which findings are reported and
+/// whether the run fails
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunOptions {
+    pub verbose: bool,
+    pub summary: bool,
+    pub fail_on_findings: bool,
+    #[serde(default)]
+    pub warnings_as_errors: bool,
+    pub max_critical: Option<usize>,
+    pub max_high: Option<usize>,
+    pub max_medium: Option<usize>,
+    pub max_low: Option<usize>,
+    pub no_exit: bool,
+    #[serde(default)]
+    pub exit_code_by_severity: bool,
+    #[serde(default)]
+    pub strict_parse: bool,
+    pub audit_macros_expanded: bool,
+    #[serde(default)]
+    pub dedupe_across_files: bool,
+    #[serde(default)]
+    pub include_tests: bool,
+    #[serde(default)]
+    pub trust_unsafe_invariants: bool,
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    #[serde(default)]
+    pub min_code_length: Option<usize>,
+    #[serde(default)]
+    pub drop_unresolved: bool,
+    /// Baseline file whose findings were left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<String>,
+    pub trusted_functions: Vec<String>,
+    pub policy_rules: usize,
+    #[serde(default)]
+    pub severity_overrides: BTreeMap<String, Severity>,
+    /// Weights in effect when severities came from the exploitability score
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exploitability: Option<Exploitability>,
+}
+
+impl RunManifest {
+    pub fn new(args: &Args, config: &Config, source: ScanSource, started_at: SystemTime) -> Self {
+        Self {
+            tool_version: cli::VERSION.to_string(),
+            ruleset_hash: ruleset_hash(),
+            config_path: config.source.as_ref().map(|path| path.display().to_string()),
+            source,
+            options: RunOptions {
+                verbose: args.verbose,
+                summary: args.summary,
+                fail_on_findings: args.fail_on_findings,
+                warnings_as_errors: args.warnings_as_errors,
+                max_critical: args.max_critical,
+                max_high: args.max_high,
+                max_medium: args.max_medium,
+                max_low: args.max_low,
+                no_exit: args.no_exit,
+                exit_code_by_severity: args.exit_code_by_severity,
+                strict_parse: args.strict_parse,
+                audit_macros_expanded: args.audit_macros_expanded,
+                dedupe_across_files: args.dedupe_across_files,
+                include_tests: config.include_tests,
+                trust_unsafe_invariants: config.trust_unsafe_invariants,
+                max_depth: config.max_depth,
+                follow_symlinks: config.follow_symlinks,
+                min_code_length: args.min_code_length,
+                drop_unresolved: args.drop_unresolved,
+                baseline: args.baseline.as_ref().map(|path| path.display().to_string()),
+                trusted_functions: config.trusted_functions.clone(),
+                policy_rules: config.policy.len(),
+                severity_overrides: config.severity.clone(),
+                exploitability: config.exploitability.enabled.then(|| config.exploitability.clone()),
+            },
+            started_at: humantime::format_rfc3339_seconds(started_at).to_string(),
+        }
+    }
+}
+
+fn ruleset_hash() -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(cli::VERSION.as_bytes());
+    for rule in rules::all_rules() {
+        for field in [rule.id, rule.kind, rule.severity, rule.message] {
+            hasher.update([0]);
+            hasher.update(field.as_bytes());
+        }
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,112 @@
+use crate::rules;
+use crate::types::{PanicClass, Severity, Vulnerability};
+
+/// One-line fix suggestion for a finding
+pub fn remediation(vuln: &Vulnerability) -> &'static str {
+    let rule = vuln.rule_id.as_str();
+
+    if rule == rules::RULE_PANIC.id {
+        return "Return an error describing the failure instead of panic!()";
+    }
+    if rule == rules::RULE_UNREACHABLE.id {
+        return "Make the state unrepresentable in the types, or return an error if it can be reached";
+    }
+    if rule == rules::RULE_TODO.id {
+        return "Implement the branch or return an error instead of todo!()";
+    }
+    if rule == rules::RULE_ASSERTION.id {
+        return "Return an error for invalid input instead of asserting";
+    }
+    if rule == rules::RULE_PROCESS_EXIT.id {
+        return "Return an error to the caller and let the binary decide to exit";
+    }
+
+    match vuln.panic_class {
+        PanicClass::CloudflareClass => {
+            "Validate untrusted config/input before use; on error keep the last known-good state"
+        }
+        PanicClass::PanicAmplification => {
+            "Handle the Err/None (poisoned lock, closed channel) instead of unwrapping"
+        }
+        PanicClass::AllocationPanic => "Cap untrusted sizes before allocating; prefer try_reserve",
+        PanicClass::ImplicitPanic => "Use .get(i) and handle the None case",
+        PanicClass::FFIBoundary => "Wrap the body in catch_unwind and return an error code",
+        PanicClass::ProcessKilling => "Return an error instead of exiting the process",
+        PanicClass::AssertionFailure => "Return an error for invalid input instead of asserting",
+        PanicClass::AssumptionPanic => "Propagate the error with ? or handle the None/Err case",
+    }
+}
+
+/// Fix-first ordering: severity weight, doubled for each context that makes
+/// a panic worse (untrusted input, hot path, async task, FFI boundary).
+pub fn priority(vuln: &Vulnerability) -> u32 {
+    let base = match vuln.severity {
+        Severity::Critical => 8,
+        Severity::High => 4,
+        Severity::Medium => 2,
+        Severity::Low => 1,
+    };
+
+    let untrusted = vuln.panic_class == PanicClass::CloudflareClass;
+    [untrusted, vuln.hot_path, vuln.in_async, vuln.in_ffi]
+        .iter()
+        .filter(|flag| **flag)
+        .fold(base, |score, _| score * 2)
+}
+
+/// The `count` findings to fix first, highest priority first; ties keep
+/// report order
+pub fn fix_first(findings: &[Vulnerability], count: usize) -> Vec<&Vulnerability> {
+    let mut ranked: Vec<_> = findings.iter().collect();
+    ranked.sort_by_key(|vuln| std::cmp::Reverse(priority(vuln)));
+    ranked.truncate(count);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(line: usize, severity: Severity, panic_class: PanicClass) -> Vulnerability {
+        let (file, code) = ("src/lib.rs".to_string(), "value.unwrap()".to_string());
+        Vulnerability::new(file, line, rules::RULE_UNWRAP.id, severity, panic_class, "General Unwrap".to_string(), code)
+    }
+
+    #[test]
+    fn explicit_panic_macros_get_their_own_fix() {
+        let with_rule = |rule_id: &str| Vulnerability {
+            rule_id: rule_id.to_string(),
+            ..finding(1, Severity::Critical, PanicClass::ImplicitPanic)
+        };
+
+        let panic = remediation(&with_rule(rules::RULE_PANIC.id));
+        let unreachable = remediation(&with_rule(rules::RULE_UNREACHABLE.id));
+        assert!(panic.contains("panic!()"), "{}", panic);
+        assert!(unreachable.contains("can be reached"), "{}", unreachable);
+        // Not the indexing advice the ImplicitPanic class would give
+        assert_ne!(panic, remediation(&with_rule(rules::RULE_INDEXING.id)));
+        assert_ne!(unreachable, remediation(&with_rule(rules::RULE_INDEXING.id)));
+    }
+
+    #[test]
+    fn critical_on_a_hot_path_ranks_above_a_plain_critical() {
+        let plain = finding(1, Severity::Critical, PanicClass::AssumptionPanic);
+        let hot = Vulnerability { hot_path: true, ..finding(2, Severity::Critical, PanicClass::AssumptionPanic) };
+        let findings = [plain, hot];
+
+        let lines: Vec<usize> = fix_first(&findings, 5).iter().map(|vuln| vuln.line).collect();
+        assert_eq!(lines, [2, 1]);
+    }
+
+    #[test]
+    fn untrusted_input_outranks_the_same_severity() {
+        let findings = [
+            finding(1, Severity::High, PanicClass::AssumptionPanic),
+            finding(2, Severity::Low, PanicClass::AssumptionPanic),
+            finding(3, Severity::High, PanicClass::CloudflareClass),
+        ];
+
+        let lines: Vec<usize> = fix_first(&findings, 2).iter().map(|vuln| vuln.line).collect();
+        assert_eq!(lines, [3, 1]);
+    }
+}
@@ -1,8 +1,15 @@
-use crate::cli::Args;
+use crate::cli::{Args, OutputFormat};
+use crate::deps::DepsReport;
+use crate::diff::{Comparison, RuleComparison};
+use crate::formats;
+use crate::remediation;
 use crate::rules;
-use crate::types::{Severity, Vulnerability};
+use crate::types::{Location, PanicClass, Report, Severity, Vulnerability};
+use anyhow::{Context, Result};
 use colored::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 pub fn print_banner() {
     println!("\n{}", "╔═══════════════════════════════════════════════════════════════════════════════╗".bright_black());
@@ -110,8 +117,7 @@ pub fn print_severity_legend() {
     println!("     {} Validate input, return Result, add size limits", "Action:".bold().yellow());
     
     println!(
-        "\n  {} {} - Can fail under specific runtime conditions",
-        "🟡",
+        "\n  🟡 {} - Can fail under specific runtime conditions",
         "MEDIUM".bold()
     );
     println!("     {} Environment variables, assertions, array indexing", "Examples:".bold());
@@ -126,17 +132,39 @@ pub fn print_severity_legend() {
     println!("     {} Review context - usually intentional and safe", "Action:".bold().bright_black());
 }
 
-pub fn print_report(
-    vulnerabilities: &mut Vec<Vulnerability>,
-    crate_name: &str,
-    version: &str,
-    args: &Args,
-) {
-    if args.json {
-        println!("{}", serde_json::to_string_pretty(vulnerabilities).unwrap());
+/// `report` in a machine format, exactly as printed; `None` for the human
+/// format, which is printed piece by piece
+pub fn render(report: &Report, format: OutputFormat) -> Option<String> {
+    let rendered = match format {
+        OutputFormat::Human => return None,
+        OutputFormat::Json => serde_json::to_string_pretty(report).unwrap() + "\n",
+        OutputFormat::Junit => formats::junit::render(report),
+        OutputFormat::Codeclimate => formats::codeclimate::render(report) + "\n",
+        OutputFormat::Sonarqube => formats::sonarqube::render(report) + "\n",
+        OutputFormat::JsonLines => formats::json_lines::render(report),
+        OutputFormat::GithubStepSummary => formats::step_summary::render(report),
+        OutputFormat::Xml => formats::xml::render(report),
+        OutputFormat::Sarif => formats::sarif::render(report) + "\n",
+    };
+    Some(rendered)
+}
+
+pub fn print_report(report: &mut Report, args: &Args) {
+    let format = args.output_format();
+    if let Some(rendered) = render(report, format) {
+        if format == OutputFormat::GithubStepSummary {
+            write_step_summary(&rendered);
+        } else {
+            print!("{}", rendered);
+        }
         return;
     }
 
+    let crate_name = report.crate_name.as_str();
+    let version = report.version.as_str();
+    let suppressed = report.suppressed;
+    let vulnerabilities = &mut report.findings;
+
     println!("\n{}", "═".repeat(80).bright_black());
     let version_display = if version == "local" {
         version.to_string()
@@ -154,6 +182,9 @@ pub fn print_report(
     if vulnerabilities.is_empty() {
         println!("{}", "✅ No panic patterns detected!".green().bold());
         println!("\nThis crate appears to handle errors gracefully.");
+        if suppressed > 0 {
+            println!("({} finding(s) suppressed by panic-audit:allow comments)", suppressed);
+        }
         return;
     }
 
@@ -182,17 +213,16 @@ pub fn print_report(
                      "(Can crash handlers)".yellow());
         }
         if medium_count > 0 {
-            println!("   {} {} {}", 
-                     "🟡", 
-                     format!("Medium:   {}", medium_count),
-                     "(Conditional failures)");
+            println!("   🟡 Medium:   {} (Conditional failures)", medium_count);
         }
         if low_count > 0 {
-            println!("   {} {} {}", 
-                     "⚪", 
+            println!("   ⚪ {} {}", 
                      format!("Low:      {}", low_count).bright_black(),
                      "(Low risk)".bright_black());
         }
+        if suppressed > 0 {
+            println!("   🔇 Suppressed: {} (panic-audit:allow comments)", suppressed);
+        }
 
         print_severity_legend();
 
@@ -227,8 +257,29 @@ pub fn print_report(
                 println!("\n{}. {}", i + 1, badge);
                 println!("   Class:   {:?}", vuln.panic_class);
                 println!("   Pattern: {}", vuln.pattern.cyan());
-                println!("   File:    {}:{}", vuln.file.bright_black(), vuln.line.yellow());
+                if args.verbose {
+                    println!("   Match:   {:?}", vuln.detection_kind);
+                }
+                println!("   File:    {}:{}", vuln.file.bright_black(), format!("{}:{}", vuln.line, vuln.column).yellow());
                 println!("   Code:    {}", vuln.code.bright_white());
+                if let Some(adapter) = &vuln.iterator_adapter {
+                    println!("   Context: inside .{}() closure", adapter);
+                }
+                if !vuln.also_at.is_empty() {
+                    println!("   Also at: {} ({} occurrences)", locations(&vuln.also_at), vuln.also_at.len() + 1);
+                }
+                if vuln.in_runtime_main {
+                    println!("   Context: async runtime entry fn (fails at startup)");
+                }
+                if vuln.in_test {
+                    println!("   Context: test code (--include-tests)");
+                }
+                if vuln.from_macro {
+                    println!("   Context: generated by a macro (line is in `cargo expand` output)");
+                }
+                if vuln.line_unresolved {
+                    println!("   Context: snippet not found in source; line is a placeholder (low confidence)");
+                }
             }
         }
 
@@ -244,13 +295,20 @@ pub fn print_report(
 
             // In verbose mode, show ALL findings (no limit)
             for (i, vuln) in other.iter().enumerate() {
+                let more = if vuln.also_at.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (+{} more)", vuln.also_at.len())
+                };
                 println!(
-                    "  {}. {:?} - {} in {}:{}",
+                    "  {}. {:?} - {} [{:?}] in {}:{}{}",
                     i + 1,
                     vuln.severity,
                     vuln.pattern.cyan(),
+                    vuln.detection_kind,
                     vuln.file.bright_black(),
-                    vuln.line.yellow()
+                    vuln.line.to_string().yellow(),
+                    more
                 );
             }
         } else if medium_count + low_count > 0 {
@@ -263,9 +321,330 @@ pub fn print_report(
                 medium_count + low_count
             );
         }
+
+        print_action_list(vulnerabilities);
     } else {
         // Summary mode
-        print_summary(vulnerabilities);
+        print_summary(vulnerabilities, suppressed);
+    }
+}
+
+/// Local-vs-published drift: JSON object, or new/fixed lists for humans
+pub fn print_comparison(comparison: &Comparison, args: &Args) {
+    if args.output_format() == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(comparison).unwrap());
+        return;
+    }
+
+    println!("\n{}", "═".repeat(80).bright_black());
+    println!(
+        "{}",
+        format!(
+            "LOCAL vs PUBLISHED {} v{}",
+            comparison.crate_name, comparison.baseline_version
+        )
+        .bold()
+    );
+    println!("{}", "─".repeat(80).bright_black());
+
+    println!("\n{} new finding(s):", comparison.new.len().to_string().red().bold());
+    for vuln in &comparison.new {
+        println!(
+            "  + {:?} {}:{} - {}",
+            vuln.severity,
+            vuln.file.bright_black(),
+            vuln.line.to_string().yellow(),
+            vuln.pattern.cyan()
+        );
+    }
+
+    println!("\n{} fixed finding(s):", comparison.fixed.len().to_string().green().bold());
+    for vuln in &comparison.fixed {
+        println!(
+            "  - {:?} {}:{} - {}",
+            vuln.severity,
+            vuln.file.bright_black(),
+            vuln.line.to_string().yellow(),
+            vuln.pattern.cyan()
+        );
+    }
+
+    println!("\n{} unchanged finding(s)", comparison.unchanged);
+    println!("\n{}", comparison.baseline_delta.summary().bold());
+}
+
+pub fn print_rule_comparison(comparison: &RuleComparison, args: &Args) {
+    if args.output_format() == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(comparison).unwrap());
+        return;
+    }
+
+    println!("\n{}", "═".repeat(80).bright_black());
+    println!(
+        "{}",
+        format!(
+            "RULES {} vs {} on {}",
+            comparison.current_config.as_deref().unwrap_or("(defaults)"),
+            comparison.other_config,
+            comparison.crate_name
+        )
+        .bold()
+    );
+    println!("{}", "─".repeat(80).bright_black());
+
+    let sides = [
+        ("current", "+", &comparison.only_current),
+        ("other", "-", &comparison.only_other),
+    ];
+    for (side, marker, findings) in sides {
+        println!("\n{} finding(s) only with the {} config:", findings.len().to_string().bold(), side);
+        for vuln in findings {
+            println!(
+                "  {} {} {:?} {}:{} - {}",
+                marker,
+                vuln.rule_id,
+                vuln.severity,
+                vuln.file.bright_black(),
+                vuln.line.to_string().yellow(),
+                vuln.pattern.cyan()
+            );
+        }
+    }
+
+    println!("\n{} finding(s) identical under both", comparison.unchanged);
+}
+
+/// `--deps`: one row per dependency with findings, riskiest first; with
+/// `--verbose`, each crate's Critical/High findings under it
+pub fn print_deps_report(deps: &DepsReport, args: &Args) {
+    if args.summary_json {
+        println!("{}", serde_json::to_string_pretty(&deps.summary()).unwrap());
+        return;
+    }
+    if args.output_format() == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(deps).unwrap());
+        return;
+    }
+
+    println!("\n{}", "═".repeat(80).bright_black());
+    println!("{}", format!("DEPENDENCIES IN {}", deps.lockfile).bold());
+    println!("{}", "─".repeat(80).bright_black());
+
+    let with_findings: Vec<_> = deps.crates.iter().filter(|krate| !krate.findings.is_empty()).collect();
+    if !with_findings.is_empty() {
+        println!("\n  {:<32} {:<14} {:>8} {:>6} {:>6}", "Crate", "Version", "Critical", "High", "Total");
+    }
+    for krate in &with_findings {
+        let critical = format!("{:>8}", krate.critical);
+        println!(
+            "  {:<32} {:<14} {} {:>6} {:>6}",
+            krate.crate_name,
+            krate.version,
+            if krate.critical > 0 { critical.red().bold() } else { critical.normal() },
+            krate.high,
+            krate.findings.len()
+        );
+        // The crate that stopped a --fail-fast run always shows why
+        let stopped_here = deps.stopped_at.as_deref() == Some(&format!("{}@{}", krate.crate_name, krate.version));
+        if args.verbose || stopped_here {
+            for vuln in krate.findings.iter().filter(|v| v.severity <= Severity::High) {
+                println!(
+                    "      {} {:?} {}:{} - {}",
+                    vuln.rule_id,
+                    vuln.severity,
+                    vuln.file.bright_black(),
+                    vuln.line.to_string().yellow(),
+                    vuln.pattern.cyan()
+                );
+            }
+        }
+    }
+
+    println!(
+        "\n{} crate(s) scanned, {} without findings",
+        deps.crates.len(),
+        deps.crates.len() - with_findings.len()
+    );
+    if deps.skipped > 0 {
+        println!("{} path/git package(s) not scanned", deps.skipped);
+    }
+    for failed in &deps.failed {
+        println!("{} {} v{}: {}", "failed:".red(), failed.crate_name, failed.version, failed.error);
+    }
+    if let Some(stopped_at) = &deps.stopped_at {
+        println!(
+            "{} {} failed the gate (--fail-fast); {} crate(s) not scanned",
+            "stopped:".red().bold(),
+            stopped_at,
+            deps.not_scanned
+        );
+    }
+    if !deps.older.is_empty() {
+        println!("{} crate(s) published before --since, not scanned:", deps.older.len());
+        for older in &deps.older {
+            println!("  {} v{} ({})", older.crate_name, older.version, older.published_at);
+        }
+    }
+}
+
+/// `--output-dir`: each crate's report as `<crate>-<version>.<ext>` in
+/// `format`, and an `index.json` (the `--summary-json` rollup naming each
+/// crate's file). Returns the index path.
+pub fn write_deps_reports(deps: &DepsReport, reports: &[Report], dir: &Path, format: OutputFormat) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let mut index = deps.summary();
+    let mut taken = HashSet::from(["index.json".to_string()]);
+    for report in reports {
+        let Some(rendered) = render(report, format) else {
+            anyhow::bail!("--output-dir needs a report format for its files");
+        };
+        let stem = format!("{}-{}", report.crate_name, report.version);
+        let file = unique_file_name(&mut taken, &stem, format.extension());
+        let path = dir.join(&file);
+        std::fs::write(&path, rendered).with_context(|| format!("Failed to write {}", path.display()))?;
+
+        let entry = index
+            .crates
+            .iter_mut()
+            .find(|krate| krate.name == report.crate_name && krate.version == report.version);
+        if let Some(entry) = entry {
+            entry.report = Some(file);
+        }
+    }
+
+    let path = dir.join("index.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&index)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// `stem.ext`, or `stem-2.ext`, `stem-3.ext`, ... when another file of this
+/// run took the name. Compared case-insensitively, as macOS and Windows
+/// file systems do.
+fn unique_file_name(taken: &mut HashSet<String>, stem: &str, extension: &str) -> String {
+    let mut name = format!("{}.{}", stem, extension);
+    let mut n = 1;
+    while !taken.insert(name.to_lowercase()) {
+        n += 1;
+        name = format!("{}-{}.{}", stem, n, extension);
+    }
+    name
+}
+
+/// Append to the file GitHub Actions renders as the job summary, falling
+/// back to stdout outside Actions
+fn write_step_summary(markdown: &str) {
+    let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY") else {
+        print!("{}", markdown);
+        return;
+    };
+
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(markdown.as_bytes()));
+    if let Err(e) = written {
+        eprintln!("⚠️  Could not write {}: {}", path.to_string_lossy(), e);
+        print!("{}", markdown);
+    }
+}
+
+/// `--pretty-context`: each Critical/High finding as its source line, a caret
+/// under the flagged expression and the rule below, read from `root`
+pub fn print_pretty_context(findings: &[Vulnerability], root: &Path) {
+    let serious: Vec<_> = findings
+        .iter()
+        .filter(|v| matches!(v.severity, Severity::Critical | Severity::High))
+        .collect();
+    if serious.is_empty() {
+        return;
+    }
+
+    println!("\n{}", "═".repeat(80).bright_black());
+    println!("{}", "CRITICAL & HIGH IN CONTEXT".bold());
+    println!("{}", "─".repeat(80).bright_black());
+
+    let mut sources: HashMap<&str, Option<String>> = HashMap::new();
+    for vuln in serious {
+        let source = sources
+            .entry(vuln.file.as_str())
+            .or_insert_with(|| std::fs::read_to_string(root.join(&vuln.file)).ok());
+        let Some(span) = vuln.span else {
+            continue;
+        };
+        // Saved reports can carry a line 0 span for synthetic tokens
+        let Some(text) = span.start.line.checked_sub(1).and_then(|index| source.as_deref()?.lines().nth(index)) else {
+            continue;
+        };
+
+        let number = span.start.line.to_string();
+        let gutter = " ".repeat(number.len());
+        println!("\n{}:{}:{}", vuln.file.bright_black(), span.start.line, span.start.column);
+        println!("{} {} {} {}", ">".red().bold(), number.yellow(), "|".bright_black(), text);
+        println!("  {} {} {}", gutter, "|".bright_black(), caret_line(text, span.start.column).red().bold());
+        println!("  {} {} {} {:?}: {}", gutter, "=".bright_black(), vuln.rule_id, vuln.severity, vuln.pattern.cyan());
+    }
+}
+
+/// Whitespace up to the 1-based character `column` of `line`, then a caret.
+/// Tabs are kept so the caret lines up however the terminal renders them.
+pub fn caret_line(line: &str, column: usize) -> String {
+    let mut caret: String = line
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    caret.push('^');
+    caret
+}
+
+fn locations(locations: &[Location]) -> String {
+    locations
+        .iter()
+        .map(|location| format!("{}:{}:{}", location.file, location.line, location.column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Top 5 fixes ranked by severity and context (untrusted input, hot path,
+/// async, FFI)
+fn print_action_list(vulnerabilities: &[Vulnerability]) {
+    println!("\n{}", "═".repeat(80).bright_black());
+    println!("{}", "WHAT TO FIX FIRST".bold());
+    println!("{}", "─".repeat(80).bright_black());
+
+    for (i, vuln) in remediation::fix_first(vulnerabilities, 5).into_iter().enumerate() {
+        let mut context = Vec::new();
+        if vuln.panic_class == PanicClass::CloudflareClass {
+            context.push("untrusted input");
+        }
+        if vuln.hot_path {
+            context.push("hot path");
+        }
+        if vuln.in_async {
+            context.push("async");
+        }
+        if vuln.in_ffi {
+            context.push("FFI");
+        }
+        let context = if context.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", context.join(", "))
+        };
+
+        println!(
+            "\n{}. {:?} {}:{} - {}{}",
+            i + 1,
+            vuln.severity,
+            vuln.file.bright_black(),
+            vuln.line.to_string().yellow(),
+            vuln.pattern.cyan(),
+            context.bright_black()
+        );
+        println!("   → {}", remediation::remediation(vuln));
     }
 }
 
@@ -285,8 +664,9 @@ fn print_panic_class_breakdown(vulnerabilities: &[Vulnerability], severity: Seve
         *class_counts.entry(class_name).or_insert(0) += 1;
     }
 
+    // Break count ties by name; HashMap iteration order would leak through
     let mut class_list: Vec<_> = class_counts.into_iter().collect();
-    class_list.sort_by(|a, b| b.1.cmp(&a.1));
+    class_list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
 
     let label = match severity {
         Severity::Critical => format!("🔴 CRITICAL ({})", items.len()).red().bold(),
@@ -302,25 +682,95 @@ fn print_panic_class_breakdown(vulnerabilities: &[Vulnerability], severity: Seve
     }
 }
 
-fn print_summary(findings: &[Vulnerability]) {
-    let mut by_severity: HashMap<&str, usize> = HashMap::new();
-
-    for f in findings {
-        let sev = match f.severity {
-            Severity::Critical => "CRITICAL",
-            Severity::High => "HIGH",
-            Severity::Medium => "MEDIUM",
-            Severity::Low => "LOW",
-        };
-        *by_severity.entry(sev).or_insert(0) += 1;
-    }
-
+fn print_summary(findings: &[Vulnerability], suppressed: usize) {
     println!("\n{}", "SUMMARY".bold());
     println!("{}", "─".repeat(80).bright_black());
 
-    for (sev, count) in by_severity {
-        println!("{:<10}: {}", sev, count);
+    // Fixed severity order keeps the summary byte-for-byte reproducible
+    for severity in Severity::ALL.iter() {
+        let count = findings.iter().filter(|f| &f.severity == severity).count();
+        if count > 0 {
+            println!("{:<10}: {}", format!("{:?}", severity).to_uppercase(), count);
+        }
     }
 
     println!("\nTotal findings: {}", findings.len());
+    if suppressed > 0 {
+        println!("Suppressed: {}", suppressed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_dir_gets_a_report_per_crate_and_an_index() {
+        use crate::deps::CrateFindings;
+
+        let sample = formats::sample_report();
+        let report = |name: &str, version: &str, findings: &[Vulnerability]| Report {
+            crate_name: name.to_string(),
+            version: version.to_string(),
+            findings: findings.to_vec(),
+            ..sample.clone()
+        };
+        let reports = [report("risky", "1.0.0", &sample.findings), report("clean", "0.2.1", &[])];
+        let deps = DepsReport {
+            lockfile: "Cargo.lock".to_string(),
+            crates: reports
+                .iter()
+                .map(|report| CrateFindings {
+                    crate_name: report.crate_name.clone(),
+                    version: report.version.clone(),
+                    critical: 0,
+                    high: 0,
+                    findings: report.findings.clone(),
+                })
+                .collect(),
+            failed: Vec::new(),
+            skipped: 0,
+            older: Vec::new(),
+            stopped_at: None,
+            not_scanned: 0,
+            unparsed: Vec::new(),
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("reports/deps");
+        let index = write_deps_reports(&deps, &reports, &out, OutputFormat::Sarif).unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(&out)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["clean-0.2.1.sarif", "index.json", "risky-1.0.0.sarif"]);
+
+        let risky = std::fs::read_to_string(out.join("risky-1.0.0.sarif")).unwrap();
+        assert_eq!(Some(risky), render(&reports[0], OutputFormat::Sarif));
+
+        let index: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(index).unwrap()).unwrap();
+        // Lowest score first, as in --summary-json
+        assert_eq!(index["crates"][0]["name"], "risky");
+        assert_eq!(index["crates"][0]["report"], "risky-1.0.0.sarif");
+        assert_eq!(index["crates"][1]["report"], "clean-0.2.1.sarif");
+    }
+
+    #[test]
+    fn colliding_file_names_get_a_suffix() {
+        let mut taken = HashSet::from(["index.json".to_string()]);
+        assert_eq!(unique_file_name(&mut taken, "foo-1.0.0", "json"), "foo-1.0.0.json");
+        assert_eq!(unique_file_name(&mut taken, "Foo-1.0.0", "json"), "Foo-1.0.0-2.json");
+        assert_eq!(unique_file_name(&mut taken, "foo-1.0.0", "json"), "foo-1.0.0-3.json");
+        assert_eq!(unique_file_name(&mut taken, "index", "json"), "index-2.json");
+    }
+
+    #[test]
+    fn caret_sits_under_the_column() {
+        assert_eq!(caret_line("    let x = v.unwrap();", 13), "            ^");
+        assert_eq!(caret_line("x", 1), "^");
+        // Tabs stay tabs; other characters count once however many bytes they take
+        assert_eq!(caret_line("\tlet é = v[i];", 11), "\t         ^");
+    }
 }
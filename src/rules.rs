@@ -1,4 +1,4 @@
-use crate::types::{PanicClass, Severity};
+use crate::types::{DetectionKind, PanicClass, Severity};
 
 #[derive(Debug, Clone)]
 pub struct Rule {
@@ -71,6 +71,34 @@ pub const RULE_PROCESS_EXIT: Rule = Rule {
     message: "process::exit() found",
 };
 
+pub const RULE_UNTRUSTED_ALLOC: Rule = Rule {
+    id: "PA010",
+    kind: "untrusted_alloc",
+    severity: "CRITICAL",
+    message: "Allocation sized by an untrusted length",
+};
+
+pub const RULE_POISON_IGNORED: Rule = Rule {
+    id: "PA011",
+    kind: "poison_ignored",
+    severity: "MEDIUM",
+    message: "Lock poisoning ignored via into_inner()",
+};
+
+pub const RULE_BUF_READ: Rule = Rule {
+    id: "PA012",
+    kind: "buf_read",
+    severity: "CRITICAL",
+    message: "bytes::Buf read panics on insufficient bytes",
+};
+
+pub const RULE_SIZE_UNDERFLOW: Rule = Rule {
+    id: "PA013",
+    kind: "size_underflow",
+    severity: "HIGH",
+    message: "Size argument subtracts runtime values (usize underflow)",
+};
+
 pub fn all_rules() -> &'static [Rule] {
     &[
         RULE_UNWRAP,
@@ -82,11 +110,69 @@ pub fn all_rules() -> &'static [Rule] {
         RULE_ASSERTION,
         RULE_MUTEX_UNWRAP,
         RULE_PROCESS_EXIT,
+        RULE_UNTRUSTED_ALLOC,
+        RULE_POISON_IGNORED,
+        RULE_BUF_READ,
+        RULE_SIZE_UNDERFLOW,
     ]
 }
 
-pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
-    let lower = code.to_lowercase();
+/// Lowercase and strip whitespace so rules can match `quote!` output
+/// (`File :: open (..)`) against source-like text (`file::open(..)`).
+pub fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Base pattern for an unwrapped regex/glob compile. The scanner adjusts
+/// its severity by whether it runs once (lazy init) or per iteration.
+pub const PATTERN_COMPILE: &str = "Unwrapped regex/glob pattern compilation";
+
+pub const PATTERN_SQLITE: &str = "Unwrapped SQLite DB operation";
+
+/// Unwrapped `try_lock`/`try_read`/`try_write` on a tokio lock; the scanner
+/// applies it in files that use `tokio::sync`
+pub const PATTERN_ASYNC_TRY_LOCK: &str = "Unwrapped async try_lock (contention panic)";
+
+/// Unwrapped stdin read, or a parse of the line it read; the scanner applies
+/// it to parses by tracking which buffers were filled from stdin.
+pub const PATTERN_STDIN: &str = "Unwrapped stdin read/parse";
+
+/// Comment directive silencing findings of the listed rule ids on its line
+/// (or the line below, when it stands alone)
+pub const ALLOW_DIRECTIVE: &str = "panic-audit:allow";
+
+/// Whether a `// panic-audit:allow PA001, PA002` comment on `line` lists
+/// `rule_id`. A directive without ids allows nothing.
+pub fn allow_comment_covers(line: &str, rule_id: &str) -> bool {
+    let Some((_, comment)) = line.split_once("//") else {
+        return false;
+    };
+    let Some((_, ids)) = comment.split_once(ALLOW_DIRECTIVE) else {
+        return false;
+    };
+    ids.split(|c: char| c == ',' || c.is_whitespace()).any(|id| id == rule_id)
+}
+
+/// Unwrapped decode of an image, CSV record or spreadsheet; the scanner
+/// applies it so the config can add parsers (`untrusted_parsers`).
+pub const PATTERN_FILE_PARSE: &str = "Unwrapped untrusted file/record parse";
+
+pub fn classify_panic(code: &str) -> (Severity, PanicClass, String, DetectionKind) {
+    let lower = normalize_code(code);
+
+    // Class 4: from_reader(File::open(p).unwrap()).unwrap() -- one expression,
+    // two ways to fail
+    if is_unwrapped_reader_over_open(&lower) {
+        return (
+            Severity::Critical,
+            PanicClass::CloudflareClass,
+            "Unwrapped deserialize of unwrapped File::open (missing file + malformed content)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
 
     // Class 4: Cloudflare-class (config/feature file loading)
     if is_cloudflare_class(&lower) {
@@ -94,6 +180,58 @@ pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
             Severity::Critical,
             PanicClass::CloudflareClass,
             "Config/Feature File Loading (Cloudflare Pattern)".to_string(),
+            DetectionKind::Heuristic,
+        );
+    }
+
+    // Class 4: missing/renamed key in an already-loaded config
+    if is_config_lookup(&lower) {
+        return (
+            Severity::Critical,
+            PanicClass::CloudflareClass,
+            "Unwrapped config key lookup (Cloudflare Pattern)".to_string(),
+            DetectionKind::Heuristic,
+        );
+    }
+
+    // <[u8; 4]>::try_from(&buf[..4]) / buf[a..b].try_into(): length mismatch
+    // in binary protocol parsing
+    if is_fixed_array_conversion(&lower) {
+        return (
+            Severity::Critical,
+            PanicClass::CloudflareClass,
+            "Fixed-size array conversion from untrusted slice + unwrap".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Corrupt or malicious compressed data: read errors, zip bombs
+    if is_decompression(&lower) {
+        return (
+            Severity::High,
+            PanicClass::AllocationPanic,
+            "Unwrapped decompression of untrusted input (DoS/panic)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // EOF or invalid UTF-8 on stdin in interactive/pipeline tools
+    if is_stdin_read(&lower) {
+        return (
+            Severity::Medium,
+            PanicClass::AssumptionPanic,
+            PATTERN_STDIN.to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Directory listing / metadata: permission denied, ENOENT
+    if is_fs_traversal(&lower) {
+        return (
+            Severity::High,
+            PanicClass::AssumptionPanic,
+            "Unwrapped filesystem traversal/metadata".to_string(),
+            DetectionKind::Literal,
         );
     }
 
@@ -107,7 +245,8 @@ pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
         return (
             Severity::Critical,
             PanicClass::AssumptionPanic,
-            "File I/O Operation".to_string()
+            "File I/O Operation".to_string(),
+            DetectionKind::Literal,
         );
     }
 
@@ -119,7 +258,18 @@ pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
         return (
             Severity::Critical,
             PanicClass::AssumptionPanic,
-            "Network Socket Operation".to_string()
+            "Network Socket Operation".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Blocking DNS resolution: fails (or yields no addresses) with external state
+    if lower.contains("to_socket_addrs") {
+        return (
+            Severity::High,
+            PanicClass::AssumptionPanic,
+            "Unwrapped DNS resolution".to_string(),
+            DetectionKind::Literal,
         );
     }
 
@@ -129,7 +279,165 @@ pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
         return (
             Severity::Critical,
             PanicClass::AssumptionPanic,
-            "HTTP Request".to_string()
+            "HTTP Request".to_string(),
+            DetectionKind::Heuristic,
+        );
+    }
+
+    // serde_json::Value accessors (missing key -> Null, wrong type -> None)
+    if is_json_value_accessor(&lower) {
+        return (
+            Severity::High,
+            PanicClass::AssumptionPanic,
+            "Unwrapped serde_json::Value accessor".to_string(),
+            DetectionKind::Heuristic,
+        );
+    }
+
+    // NonZero*::new(x) is None when x == 0; a nonzero literal can't fail
+    if let Some(arg) = unwrapped_nonzero_new_arg(&lower) {
+        let severity = if is_nonzero_int_literal(arg) {
+            Severity::Low
+        } else {
+            Severity::Medium
+        };
+        return (
+            severity,
+            PanicClass::AssumptionPanic,
+            "Unwrapped NonZero::new (zero value panic)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // tokio::time::timeout(..).await is Err(Elapsed) when upstream is slow
+    if call_then_await_unwrap(&lower, "timeout") {
+        return (
+            Severity::High,
+            PanicClass::AssumptionPanic,
+            "Unwrapped timeout (elapsed → panic)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Async channels (tokio::sync::mpsc, flume): Err/None once the other
+    // side is dropped. `.send()` without a payload is an HTTP request.
+    if call_then_await_unwrap(&lower, ".recv") ||
+       (call_then_await_unwrap(&lower, ".send") && !lower.contains(".send().await")) {
+        return (
+            Severity::High,
+            PanicClass::PanicAmplification,
+            "Unwrapped async channel op".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // std::process::Command: missing binary, permission denied, ... Only a
+    // chain built by `Command::new`: `response.status()` or a thread
+    // builder's `spawn()` runs no subprocess
+    if has_command_new(&lower) && runs_command(&lower) {
+        return subprocess_panic(DetectionKind::Literal);
+    }
+
+    // base64/hex decoding of malformed network data
+    if is_encoded_decode(&lower) {
+        return (
+            Severity::High,
+            PanicClass::AssumptionPanic,
+            "Unwrapped base64/hex decode of untrusted input".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Weak::upgrade is None once the referent has been dropped
+    if call_then_unwrap(&lower, ".upgrade") {
+        return (
+            Severity::Medium,
+            PanicClass::AssumptionPanic,
+            "Unwrapped Weak::upgrade (dangling reference panic)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Arc/Rc::get_mut is None while other strong or weak references exist
+    if call_then_unwrap(&lower, "rc::get_mut") {
+        return (
+            Severity::Medium,
+            PanicClass::AssumptionPanic,
+            "Unwrapped Arc/Rc::get_mut (shared reference panic)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // clap accessors are None when the arg was not given (or not defined)
+    if [".get_one", ".get_many", ".value_of", ".values_of"].iter().any(|call| call_then_unwrap(&lower, call)) {
+        return (
+            Severity::Medium,
+            PanicClass::AssumptionPanic,
+            "Unwrapped CLI arg accessor (missing arg panic)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Consuming a poisoned lock at teardown; other `into_inner` results
+    // (BufWriter, ...) stay general unwraps
+    if is_lock_into_inner(&lower) {
+        return (
+            Severity::Medium,
+            PanicClass::PanicAmplification,
+            "Unwrapped lock into_inner (poison panic on teardown)".to_string(),
+            DetectionKind::Heuristic,
+        );
+    }
+
+    // The re-acquired guard carries poisoning from any thread that panicked
+    // while holding the mutex
+    if is_condvar_wait(&lower) {
+        return (
+            Severity::Medium,
+            PanicClass::PanicAmplification,
+            "Unwrapped Condvar wait (poison panic)".to_string(),
+            DetectionKind::Heuristic,
+        );
+    }
+
+    // Regex/glob compilation; severity is refined from call-site context
+    if unwrapped_pattern_compile_arg(&lower).is_some() {
+        return (
+            Severity::Medium,
+            PanicClass::AssumptionPanic,
+            PATTERN_COMPILE.to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Layout construction fails on overflowing size or non-power-of-two align
+    if call_then_unwrap(&lower, "layout::from_size_align") ||
+        call_then_unwrap(&lower, "layout::array") {
+        return (
+            Severity::Medium,
+            PanicClass::AllocationPanic,
+            "Unwrapped Layout construction (invalid size/align panic)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Malformed links/headers from user input
+    if ["url::parse", "uri::from_str", "uri::try_from"].iter().any(|call| call_then_unwrap(&lower, call)) {
+        return (
+            Severity::High,
+            PanicClass::AssumptionPanic,
+            "Unwrapped URL/URI parse of untrusted input".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // Hex/octal strings and bignums from the wire fail on any stray digit
+    if is_radix_parse(&lower) {
+        return (
+            Severity::High,
+            PanicClass::AssumptionPanic,
+            "Unwrapped radix/number parse of untrusted input".to_string(),
+            DetectionKind::Literal,
         );
     }
 
@@ -138,7 +446,8 @@ pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
         return (
             Severity::High,
             PanicClass::AllocationPanic,
-            "Allocation with Potential Untrusted Size".to_string()
+            "Allocation with Potential Untrusted Size".to_string(),
+            DetectionKind::Heuristic,
         );
     }
 
@@ -152,7 +461,8 @@ pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
         return (
             Severity::High,
             PanicClass::AssumptionPanic,
-            "Parsing Operation".to_string()
+            "Parsing Operation".to_string(),
+            DetectionKind::Heuristic,
         );
     }
 
@@ -166,7 +476,29 @@ pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
         return (
             Severity::High,
             PanicClass::AssumptionPanic,
-            "Database Operation".to_string()
+            "Database Operation".to_string(),
+            DetectionKind::Heuristic,
+        );
+    }
+
+    // rusqlite calls that name the crate; bare `conn.prepare(..)` is
+    // recognized by the scanner from the file's imports
+    if lower.contains("sqlite") && is_sqlite_op(&lower) {
+        return (
+            Severity::High,
+            PanicClass::AssumptionPanic,
+            PATTERN_SQLITE.to_string(),
+            DetectionKind::Heuristic,
+        );
+    }
+
+    // Unavailable in some sandboxes/containers (deleted cwd, no /proc)
+    if call_then_unwrap(&lower, "env::current_dir") || call_then_unwrap(&lower, "env::current_exe") {
+        return (
+            Severity::Medium,
+            PanicClass::AssumptionPanic,
+            "Unwrapped current_dir/current_exe (environment assumption)".to_string(),
+            DetectionKind::Literal,
         );
     }
 
@@ -175,21 +507,52 @@ pub fn classify_panic(code: &str) -> (Severity, PanicClass, String) {
         return (
             Severity::Medium,
             PanicClass::AssumptionPanic,
-            "Environment Variable".to_string()
+            "Environment Variable".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // iter.position(..)/find(..) is None when nothing matches
+    if is_search_result(&lower) {
+        return (
+            Severity::Medium,
+            PanicClass::AssumptionPanic,
+            "Unwrapped search result (no-match panic)".to_string(),
+            DetectionKind::Literal,
+        );
+    }
+
+    // a.zip(b).map(..).unwrap(): any None along the chain panics
+    if is_option_combinator_chain(&lower) {
+        return (
+            Severity::Medium,
+            PanicClass::AssumptionPanic,
+            "Unwrapped option combinator chain".to_string(),
+            DetectionKind::Heuristic,
         );
     }
 
     (
         Severity::Low,
         PanicClass::AssumptionPanic,
-        "General Unwrap".to_string()
+        "General Unwrap".to_string(),
+        DetectionKind::Literal,
     )
 }
 
+/// `serde_json::from_reader(File::open(p).unwrap()).unwrap()`, also through
+/// `BufReader::new(..)`
+fn is_unwrapped_reader_over_open(code: &str) -> bool {
+    ["from_reader", "deserialize_from"].iter().any(|reader| {
+        unwrapped_call_args(code, reader).any(|arg| call_then_unwrap(arg, "file::open"))
+    })
+}
+
 fn is_cloudflare_class(code: &str) -> bool {
     let has_file_op = code.contains("file::open") ||
                       code.contains("read_to_string") ||
-                      code.contains("fs::read");
+                      code.contains("fs::read") ||
+                      is_fs_traversal(code);
     
     let config_keywords = [".toml", ".yaml", ".json", ".ini", ".conf", 
                           "config", "settings", "feature"];
@@ -198,13 +561,359 @@ fn is_cloudflare_class(code: &str) -> bool {
     has_file_op && has_config
 }
 
+/// A `let` initializer that loads or parses a config/feature file:
+/// `fs::read_to_string("features.json")`, `toml::from_str(&text)`,
+/// `Settings::load()`
+pub fn is_config_load(code: &str) -> bool {
+    let config_names = ["config", "settings", "cfg", "conf", "toml", "feature"];
+    let loads = ["::from_str(", "::from_slice(", "::from_reader(", "::from_value(", "load("];
+    is_cloudflare_class(code) ||
+        (config_names.iter().any(|name| code.contains(name)) &&
+            loads.iter().any(|load| code.contains(load)))
+}
+
+/// `config.get("port").unwrap()` or `settings["db"]["url"].as_str().unwrap()`
+/// on something named like a config. Only the unwrap of the lookup itself
+/// counts: in `config.get("port").unwrap().as_u64().unwrap()` the outer
+/// unwrap is an accessor, not a second lookup.
+fn is_config_lookup(code: &str) -> bool {
+    let config_names = ["config", "settings", "cfg", "conf", "toml"];
+    if !config_names.iter().any(|name| code.contains(name)) {
+        return false;
+    }
+
+    let literal_get = code.match_indices(".get(\"").any(|(start, _)| {
+        let args = &code[start + ".get(".len()..];
+        closing_paren(args).is_some_and(|end| is_final_unwrap(&args[end + 1..]))
+    });
+    let value_index = code.contains("[\"") &&
+        JSON_VALUE_ACCESSORS.iter().any(|call| call_suffixes(code, call).any(is_final_unwrap));
+    literal_get || value_index
+}
+
+fn is_decompression(code: &str) -> bool {
+    let decoders = ["gzdecoder", "multigzdecoder", "zlibdecoder", "deflatedecoder",
+                    "bzdecoder", "xzdecoder", "zstd::", "brotli::"];
+    if !decoders.iter().any(|decoder| code.contains(decoder)) {
+        return false;
+    }
+
+    let reads = [".read_to_end", ".read_to_string", ".read_exact", ".read",
+                 "io::copy", "decode_all", ".unpack"];
+    reads.iter().any(|call| call_then_unwrap(code, call))
+}
+
+fn is_lock_into_inner(code: &str) -> bool {
+    let lock_like = ["mutex", "rwlock", "lock", "try_unwrap"]
+        .iter()
+        .any(|hint| code.contains(hint));
+    lock_like && call_then_unwrap(code, ".into_inner")
+}
+
+/// `wait`/`wait_timeout`/`wait_while` followed by unwrap, with a condvar in
+/// sight (`cvar`, `self.condvar`, `Condvar::new()`)
+fn is_condvar_wait(code: &str) -> bool {
+    let condvar_like = ["cvar", "condvar", "cond_var"]
+        .iter()
+        .any(|hint| code.contains(hint));
+    condvar_like && [".wait", ".wait_timeout", ".wait_while"].iter().any(|call| call_then_unwrap(code, call))
+}
+
+/// Non-blocking lock attempt followed by unwrap: fails whenever the lock is
+/// held, so it panics under contention
+pub fn is_try_lock(code: &str) -> bool {
+    let calls = [".try_lock", ".try_read", ".try_write",
+                 ".try_lock_owned", ".try_read_owned", ".try_write_owned"];
+    calls.iter().any(|call| call_then_unwrap(code, call))
+}
+
+/// rusqlite statement and row calls followed by unwrap: bad SQL, schema or
+/// column type mismatch
+pub fn is_sqlite_op(code: &str) -> bool {
+    let calls = [".prepare", ".prepare_cached", ".query", ".query_map", ".query_row",
+                 ".query_and_then", ".execute", ".execute_batch"];
+    calls.iter().any(|call| call_then_unwrap(code, call)) ||
+        (code.contains(".get::<_,") && call_then_unwrap(code, ".get"))
+}
+
+/// image/csv/calamine loads and record reads followed by unwrap; malformed
+/// input files crash the pipeline that ingests them
+const FILE_PARSE_CALLS: [&str; 12] = [
+    "image::load_from_memory",
+    "image::load_from_memory_with_format",
+    "image::load",
+    "image::open",
+    "imagereader::open",
+    ".records().next",
+    ".byte_records().next",
+    ".deserialize().next",
+    "csv::reader::from_path",
+    "open_workbook",
+    "open_workbook_auto",
+    ".worksheet_range",
+];
+
+/// A built-in file/record parse, or a call in `extra` (as written in the
+/// config, e.g. `mylib::parse_record`), followed by unwrap
+pub fn is_untrusted_file_parse(code: &str, extra: &[String]) -> bool {
+    FILE_PARSE_CALLS.iter().any(|call| call_then_unwrap(code, call)) ||
+        extra.iter().any(|call| call_then_unwrap(code, &normalize_code(call)))
+}
+
+fn is_stdin_read(code: &str) -> bool {
+    code.contains("stdin") &&
+        [".read_line", ".read_to_string"].iter().any(|call| call_then_unwrap(code, call))
+}
+
+fn is_fs_traversal(code: &str) -> bool {
+    let calls = ["read_dir", "metadata", "canonicalize", "create_dir_all"];
+    calls.iter().any(|call| call_then_unwrap(code, call))
+}
+
+fn is_fixed_array_conversion(code: &str) -> bool {
+    if call_then_unwrap(code, "]>::try_from") {
+        return true;
+    }
+
+    // `.try_into()` on a range-sliced buffer is almost always `[T; N]`
+    call_then_unwrap(code, ".try_into") && code.contains("..") && code.contains('[')
+}
+
+fn is_encoded_decode(code: &str) -> bool {
+    let decoders = ["base64::decode", "hex::decode", "standard.decode",
+                    "standard_no_pad.decode", "url_safe.decode",
+                    "url_safe_no_pad.decode", "engine.decode"];
+    decoders.iter().any(|call| call_then_unwrap(code, call))
+}
+
+const JSON_VALUE_ACCESSORS: [&str; 7] = [".as_str", ".as_i64", ".as_u64", ".as_f64", ".as_bool",
+                                         ".as_object", ".as_array"];
+
+fn is_json_value_accessor(code: &str) -> bool {
+    JSON_VALUE_ACCESSORS.iter().any(|call| call_then_unwrap(code, call))
+}
+
+fn is_search_result(code: &str) -> bool {
+    let searches = [".position", ".rposition", ".find", ".rfind"];
+    searches.iter().any(|call| call_then_unwrap(code, call))
+}
+
+fn is_option_combinator_chain(code: &str) -> bool {
+    let combinators = [".zip", ".and_then", ".filter", ".map", ".or_else", ".xor"];
+    combinators.iter().any(|call| call_then_unwrap(code, call))
+}
+
+/// Argument text of `NonZeroU32::new(arg)` / `NonZero::<u32>::new(arg)`
+/// when the call is immediately unwrapped.
+fn unwrapped_nonzero_new_arg(code: &str) -> Option<&str> {
+    code.match_indices("nonzero").find_map(|(start, _)| {
+        let rest = code[start + "nonzero".len()..].trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+        let args = skip_turbofish(rest).strip_prefix("::new(")?;
+        let end = closing_paren(args)?;
+        is_unwrap_call(&args[end + 1..]).then(|| &args[..end])
+    })
+}
+
+/// Argument text of `Regex::new(arg)`, `RegexSet::new(arg)`,
+/// `glob::Pattern::new(arg)` or `glob::glob(arg)` when the result is
+/// immediately unwrapped. Expects normalized code.
+pub fn unwrapped_pattern_compile_arg(code: &str) -> Option<&str> {
+    ["regex::new", "regexset::new", "pattern::new", "glob::glob"]
+        .iter()
+        .find_map(|call| unwrapped_call_args(code, call).next())
+}
+
+/// `"^[a-z]+$"`, `r"\d+"`, `r#"..."#` -- a pattern fixed at compile time
+pub fn is_literal_pattern(arg: &str) -> bool {
+    let arg = arg.strip_prefix('&').unwrap_or(arg);
+    let quoted = arg.starts_with('"') && arg.ends_with('"');
+    let raw = arg.starts_with('r') &&
+        arg[1..].trim_start_matches('#').starts_with('"') &&
+        arg.trim_end_matches('#').ends_with('"');
+    (quoted || raw) && arg.len() > 1
+}
+
+/// `1`, `4_096`, `8u32`, `0x10` -- but not `0` or anything non-literal
+fn is_nonzero_int_literal(arg: &str) -> bool {
+    let digits: String = arg.chars().filter(|c| *c != '_').collect();
+    let digits = ["u8", "u16", "u32", "u64", "u128", "usize",
+                  "i8", "i16", "i32", "i64", "i128", "isize"]
+        .iter()
+        .find_map(|suffix| digits.strip_suffix(suffix))
+        .unwrap_or(&digits);
+
+    match digits.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() &&
+            hex.chars().all(|c| c.is_ascii_hexdigit()) &&
+            hex.chars().any(|c| c != '0'),
+        None => !digits.is_empty() &&
+            digits.chars().all(|c| c.is_ascii_digit()) &&
+            digits.chars().any(|c| c != '0'),
+    }
+}
+
+/// `m.lock().unwrap_or_else(|p| p.into_inner())` (or `read`/`write`):
+/// recovers from poisoning by using whatever state the panicking holder left.
+/// Expects normalized code.
+pub fn is_poison_ignored(code: &str) -> bool {
+    [".lock", ".read", ".write"].iter().any(|call| {
+        call_suffixes(code, call).any(|rest| {
+            rest.strip_prefix(".unwrap_or_else(")
+                .and_then(|args| closing_paren(args).map(|end| &args[..end]))
+                .is_some_and(|handler| handler.contains("into_inner"))
+        })
+    })
+}
+
+/// True when a call to `call` (e.g. `.as_str`, `nonzerou32::new`) in
+/// normalized code is immediately followed by `.unwrap()` or `.expect(..)`.
+fn call_then_unwrap(code: &str, call: &str) -> bool {
+    call_suffixes(code, call).any(is_unwrap_call)
+}
+
+/// Unwrapped `from_str_radix(..)` or num-bigint parse (`BigInt::from_str`, `.parse::<BigUint>()`)
+fn is_radix_parse(lower: &str) -> bool {
+    const CALLS: [&str; 7] = [
+        "::from_str_radix",
+        "bigint::from_str",
+        "biguint::from_str",
+        "bigint::parse_bytes",
+        "biguint::parse_bytes",
+        ".parse::<bigint>",
+        ".parse::<biguint>",
+    ];
+    CALLS.iter().any(|call| call_then_unwrap(lower, call))
+}
+
+/// Like `call_then_unwrap`, for `call(..).await.unwrap()`.
+fn call_then_await_unwrap(code: &str, call: &str) -> bool {
+    call_suffixes(code, call)
+        .any(|rest| rest.strip_prefix(".await").is_some_and(is_unwrap_call))
+}
+
+/// `.output()`, `.status()` or `.spawn()` followed by unwrap/expect.
+/// Expects normalized code.
+pub fn runs_command(code: &str) -> bool {
+    call_then_unwrap(code, ".output") ||
+        call_then_unwrap(code, ".status") ||
+        call_then_unwrap(code, ".spawn")
+}
+
+/// Classification of an unwrapped `Command` run
+pub fn subprocess_panic(kind: DetectionKind) -> (Severity, PanicClass, String, DetectionKind) {
+    (
+        Severity::High,
+        PanicClass::AssumptionPanic,
+        "Unwrapped subprocess execution".to_string(),
+        kind,
+    )
+}
+
+/// `command::new(` as a whole path segment, so `mycommand::new(` doesn't count
+fn has_command_new(code: &str) -> bool {
+    code.match_indices("command::new(")
+        .any(|(start, _)| !code[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+}
+
+/// Argument text of each `call(..)` that is immediately unwrapped.
+fn unwrapped_call_args<'a>(code: &'a str, call: &'a str) -> impl Iterator<Item = &'a str> {
+    code.match_indices(call).filter_map(move |(start, _)| {
+        let args = skip_turbofish(&code[start + call.len()..]).strip_prefix('(')?;
+        let end = closing_paren(args)?;
+        is_unwrap_call(&args[end + 1..]).then(|| &args[..end])
+    })
+}
+
+/// The code following each complete `call(..)` argument list.
+fn call_suffixes<'a>(code: &'a str, call: &'a str) -> impl Iterator<Item = &'a str> {
+    code.match_indices(call).filter_map(move |(start, _)| {
+        let rest = skip_turbofish(&code[start + call.len()..]);
+        let args = rest.strip_prefix('(')?;
+        closing_paren(args).map(|end| &args[end + 1..])
+    })
+}
+
+/// An unwrap/expect call that ends the expression
+fn is_final_unwrap(rest: &str) -> bool {
+    let Some(args) = rest.strip_prefix(".unwrap(").or_else(|| rest.strip_prefix(".expect(")) else {
+        return false;
+    };
+    closing_paren(args).is_some_and(|end| args[end + 1..].is_empty())
+}
+
+fn is_unwrap_call(rest: &str) -> bool {
+    rest.starts_with(".unwrap(") ||
+        rest.starts_with(".expect(") ||
+        rest.starts_with(".unwrap_unchecked(")
+}
+
+/// Skip a `::<..>` turbofish so `get_one::<T>(..)` matches like `get_one(..)`.
+fn skip_turbofish(rest: &str) -> &str {
+    let Some(generics) = rest.strip_prefix("::<") else {
+        return rest;
+    };
+
+    let mut depth = 1;
+    for (i, c) in generics.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &generics[i + 1..];
+                }
+            }
+            _ => {}
+        }
+    }
+    rest
+}
+
+/// Byte offset of the `)` closing an argument list whose `(` was just consumed.
+fn closing_paren(args: &str) -> Option<usize> {
+    let mut depth = 1;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `bytes::Buf` accessors that panic when fewer bytes remain than they
+/// consume: `get_u8`, `get_u32_le`, `get_f64`, `copy_to_bytes`, `advance`
+/// (the `_be` spelling some readers use is accepted too)
+pub fn is_buf_read(method: &str) -> bool {
+    if matches!(method, "copy_to_bytes" | "advance") {
+        return true;
+    }
+    let Some(ty) = method.strip_prefix("get_") else {
+        return false;
+    };
+    let ty = ty.trim_end_matches("_le").trim_end_matches("_be").trim_end_matches("_ne");
+    if let Some(bits) = ty.strip_prefix('f') {
+        return matches!(bits, "32" | "64");
+    }
+    let bits = ty.strip_prefix(['u', 'i']).unwrap_or_default();
+    matches!(bits, "8" | "16" | "32" | "64" | "128")
+}
+
 pub fn is_false_positive(code: &str) -> bool {
-    let lower = code.to_lowercase();
+    let lower = normalize_code(code);
     
     // Filter false positives
-    if lower.contains("arc::try_unwrap") || 
-       lower.contains("rc::try_unwrap") {
-        return true; // Memory management, not I/O
+    // Memory management, not I/O -- unless the unwrapped value is a lock
+    // being consumed at teardown
+    if (lower.contains("arc::try_unwrap") || lower.contains("rc::try_unwrap")) &&
+       !is_lock_into_inner(&lower) {
+        return true;
     }
     
     if (lower.contains("self.inner") || lower.contains(".inner()")) && 
@@ -1,35 +1,319 @@
-use crate::rules::{classify_panic, is_false_positive};
-use crate::types::{PanicClass, Severity, Vulnerability};
+use crate::config::Config;
+use crate::rules::{self, classify_panic, is_false_positive, normalize_code};
+use crate::types::{DetectionKind, PanicClass, Position, Severity, SourceSpan, Vulnerability};
+use proc_macro2::{TokenStream, TokenTree};
 use quote::quote;
-use syn::{visit::Visit, ExprIndex, ExprMethodCall, ItemFn, Macro};
+use syn::spanned::Spanned;
+use std::collections::HashSet;
+use syn::{
+    visit::Visit, Attribute, BinOp, Block, Expr, ExprAsync, ExprBinary, ExprCall, ExprClosure,
+    ExprForLoop, ExprIndex, ExprLoop, ExprMethodCall, ExprPath, ExprRepeat, ExprWhile, File, FnArg,
+    ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemUse, Local, Macro, Member, Meta, Pat, Signature, Stmt,
+    Type, UseTree, Visibility,
+};
+
+/// Length limit of the code snippet stored on each finding
+const SNIPPET_CHARS: usize = 120;
+
+/// Adapters and reducers whose closure runs once per element: one bad element
+/// aborts the whole iteration
+const ITERATOR_ADAPTERS: [&str; 12] = [
+    "map", "filter", "filter_map", "flat_map", "for_each", "inspect",
+    "take_while", "skip_while", "fold", "try_fold", "reduce", "scan",
+];
+
+/// Methods that produce or transform an iterator, used to tell `iter.map(..)`
+/// apart from `Option::map(..)`
+const ITERATOR_METHODS: [&str; 18] = [
+    "iter", "iter_mut", "into_iter", "chars", "char_indices", "bytes", "lines",
+    "split", "split_whitespace", "keys", "values", "drain", "windows", "chunks",
+    "enumerate", "zip", "skip", "take",
+];
+
+/// rayon methods that start a parallel iterator; a panic in one of its
+/// closures aborts the pool's work and resurfaces at the join point
+const PARALLEL_ITERATOR_METHODS: [&str; 9] = [
+    "par_iter", "par_iter_mut", "into_par_iter", "par_bridge", "par_chunks",
+    "par_chunks_mut", "par_windows", "par_lines", "par_drain",
+];
+
+/// Methods whose closure argument runs once, on first use
+const INIT_METHODS: [&str; 4] = ["get_or_init", "get_or_try_init", "call_once", "call_once_force"];
+
+/// Lazy wrappers whose `::new(..)` closure runs once, on first use
+const LAZY_TYPES: [&str; 3] = ["Lazy", "LazyLock", "LazyCell"];
+
+/// Unwrap rules that `trusted_functions` in the config downgrades to Low
+const TRUSTED_FN_RULES: [&str; 3] = [
+    rules::RULE_UNWRAP.id,
+    rules::RULE_EXPECT.id,
+    rules::RULE_MUTEX_UNWRAP.id,
+];
+
+/// Rules that `trust_unsafe_invariants` downgrades to Low: the unwrap and
+/// indexing an unsafe length or init invariant would make safe
+const UNSAFE_INVARIANT_RULES: [&str; 2] = [rules::RULE_UNWRAP.id, rules::RULE_INDEXING.id];
+
+/// Panic-capable rules escalated to Critical inside `Drop::drop`
+const DROP_PANIC_RULES: [&str; 4] = [
+    rules::RULE_UNWRAP.id,
+    rules::RULE_EXPECT.id,
+    rules::RULE_INDEXING.id,
+    rules::RULE_PANIC.id,
+];
+
+/// Types whose `Index` impl panics on a missing key
+const MAP_TYPES: [&str; 3] = ["HashMap", "BTreeMap", "IndexMap"];
+
+/// Crates whose `#[<crate>::main]` attribute wraps `main` in an async runtime
+const RUNTIME_MAIN_CRATES: [&str; 4] = ["tokio", "actix_web", "actix_rt", "async_std"];
+
+/// Collection lookups returning an `Option` that callers tend to unwrap
+const LOOKUP_METHODS: [&str; 10] = [
+    "get", "get_mut", "remove", "first", "last", "first_mut", "last_mut", "pop", "front", "back",
+];
+
+/// Methods whose first argument is a length or capacity
+const SIZE_METHODS: [&str; 4] = ["resize", "truncate", "reserve", "reserve_exact"];
+
+/// Option adapters that can sit between a lookup and its unwrap
+const LOOKUP_ADAPTERS: [&str; 4] = ["copied", "cloned", "as_ref", "as_mut"];
+
+const COMPOUND_LOCK_PATTERN: &str = "Compound lock + lookup unwrap (two panic points)";
+
+const CONFIG_BOUNDS_PATTERN: &str = "Assuming bounds on config-derived data";
+
+const ADAPTER_PATTERN: &str = "Panic in iterator adapter closure (per-element crash)";
+
+const PARALLEL_ADAPTER_PATTERN: &str = "Panic in parallel iterator closure (pool abort)";
+
+const ITERATOR_NEXT_PATTERN: &str = "Panic in Iterator::next (crashes all consumers)";
+
+const DROP_PATTERN: &str = "Panic in Drop::drop (aborts the process while unwinding)";
+
+/// Context saved when entering a function body and restored on exit
+struct FnContext {
+    in_extern_fn: bool,
+    in_async: bool,
+    in_runtime_main: bool,
+    in_hot_fn: bool,
+    in_iterator_next: bool,
+    in_drop_impl: bool,
+    in_pub_fn: bool,
+    loop_depth: usize,
+    init_depth: usize,
+    iterator_adapter: Option<String>,
+    in_parallel_adapter: bool,
+    after_unsafe_stmt: bool,
+    current_fn: Option<String>,
+    map_bindings: HashSet<String>,
+    value_bindings: HashSet<String>,
+    command_bindings: HashSet<String>,
+    untrusted_lengths: HashSet<String>,
+    stdin_buffers: HashSet<String>,
+    config_bindings: HashSet<String>,
+}
+
 
 pub struct Scanner {
     #[allow(dead_code)]
     pub crate_name: String,
     pub current_file: String,
     pub current_source: String,  // Store source content for line lookup
+    /// The current file mentions (r)sqlite, so bare `conn.prepare(..)` is a DB call
+    pub uses_sqlite: bool,
+    /// The current file mentions `tokio::sync`, so `try_lock` is an async lock's
+    pub uses_tokio_sync: bool,
+    /// The current file contains a `panic-audit:allow` comment
+    pub has_allow_comments: bool,
     pub in_test_code: bool,
-    #[allow(dead_code)]
-    pub in_unsafe_block: bool,
     pub in_extern_fn: bool,
+    /// `use serde_json::Value` is in scope in the current file, so a bare
+    /// `Value` type is the JSON one
+    pub json_value_in_scope: bool,
+    /// Locals and parameters of the current fn holding a `serde_json::Value`
+    pub value_bindings: HashSet<String>,
+    /// Locals and parameters of the current fn holding a `process::Command`
+    pub command_bindings: HashSet<String>,
+    pub in_async: bool,
+    /// Inside a `#[tokio::main]`/`#[actix_web::main]` entry fn
+    pub in_runtime_main: bool,
+    /// Inside a fn annotated `#[panic_audit::hot_path]`
+    pub in_hot_fn: bool,
+    /// Trait of the enclosing `impl Trait for T` block (last path segment)
+    pub impl_trait: Option<String>,
+    /// Inside `fn next` of an `impl Iterator for T`
+    pub in_iterator_next: bool,
+    /// Inside `fn drop` of an `impl Drop for T`
+    pub in_drop_impl: bool,
+    /// The innermost enclosing `fn` is `pub`
+    pub in_pub_fn: bool,
+    pub loop_depth: usize,
+    pub init_depth: usize,
+    pub iterator_adapter: Option<String>,
+    /// The enclosing adapter closure runs on a rayon parallel iterator
+    pub in_parallel_adapter: bool,
+    /// The current statement directly follows an `unsafe { .. }` statement in
+    /// its block; nested blocks, closures and loops clear it, so only the
+    /// statement's own top-level expressions count
+    pub after_unsafe_stmt: bool,
+    /// Name of the innermost enclosing `fn`
+    pub current_fn: Option<String>,
+    /// Locals and parameters of the current fn known to be maps
+    pub map_bindings: HashSet<String>,
+    /// Locals of the current fn assigned from a read/parse/decode call, or
+    /// computed from such a local
+    pub untrusted_lengths: HashSet<String>,
+    /// Locals of the current fn filled by `stdin().read_line(&mut buf)`
+    pub stdin_buffers: HashSet<String>,
+    /// Locals and parameters of the current fn holding a loaded config or
+    /// data derived from one
+    pub config_bindings: HashSet<String>,
+    /// Span of the expression being checked, attached to findings it produces
+    pub current_span: Option<SourceSpan>,
+    /// Inner unwrap already reported as part of an outer finding (compound
+    /// lock + lookup, deserialize over `File::open`), so its own visit
+    /// doesn't report it again
+    pub merged_unwrap: Option<SourceSpan>,
+    pub config: Config,
     pub vulnerabilities: Vec<Vulnerability>,
+    /// Findings silenced by a `panic-audit:allow` comment
+    pub suppressed: Vec<Vulnerability>,
 }
 
 impl Scanner {
-    pub fn new(crate_name: String) -> Self {
+    pub fn new(crate_name: String, config: Config) -> Self {
         Self {
             crate_name,
             current_file: String::new(),
             current_source: String::new(),
+            uses_sqlite: false,
+            uses_tokio_sync: false,
+            has_allow_comments: false,
             in_test_code: false,
-            in_unsafe_block: false,
             in_extern_fn: false,
+            json_value_in_scope: false,
+            value_bindings: HashSet::new(),
+            command_bindings: HashSet::new(),
+            in_async: false,
+            in_runtime_main: false,
+            in_hot_fn: false,
+            impl_trait: None,
+            in_iterator_next: false,
+            in_drop_impl: false,
+            in_pub_fn: false,
+            loop_depth: 0,
+            init_depth: 0,
+            iterator_adapter: None,
+            in_parallel_adapter: false,
+            after_unsafe_stmt: false,
+            current_fn: None,
+            map_bindings: HashSet::new(),
+            untrusted_lengths: HashSet::new(),
+            stdin_buffers: HashSet::new(),
+            config_bindings: HashSet::new(),
+            current_span: None,
+            merged_unwrap: None,
+            config,
             vulnerabilities: Vec::new(),
+            suppressed: Vec::new(),
         }
     }
 
-    /// Extract line number from quote! output by searching source
+    fn enter_fn(&mut self, sig: &Signature) -> FnContext {
+        let outer = FnContext {
+            in_extern_fn: self.in_extern_fn,
+            in_async: self.in_async,
+            in_runtime_main: self.in_runtime_main,
+            in_hot_fn: self.in_hot_fn,
+            in_iterator_next: self.in_iterator_next,
+            in_drop_impl: self.in_drop_impl,
+            in_pub_fn: self.in_pub_fn,
+            loop_depth: self.loop_depth,
+            init_depth: self.init_depth,
+            iterator_adapter: self.iterator_adapter.take(),
+            in_parallel_adapter: std::mem::take(&mut self.in_parallel_adapter),
+            after_unsafe_stmt: std::mem::take(&mut self.after_unsafe_stmt),
+            current_fn: self.current_fn.replace(sig.ident.to_string()),
+            map_bindings: std::mem::take(&mut self.map_bindings),
+            value_bindings: std::mem::take(&mut self.value_bindings),
+            command_bindings: std::mem::take(&mut self.command_bindings),
+            untrusted_lengths: std::mem::take(&mut self.untrusted_lengths),
+            stdin_buffers: std::mem::take(&mut self.stdin_buffers),
+            config_bindings: std::mem::take(&mut self.config_bindings),
+        };
+
+        for input in &sig.inputs {
+            if let FnArg::Typed(arg) = input {
+                if is_map_type(&arg.ty) {
+                    self.map_bindings.extend(pat_ident(&arg.pat));
+                }
+                if is_json_value_type(&arg.ty, self.json_value_in_scope) {
+                    self.value_bindings.extend(pat_ident(&arg.pat));
+                }
+                if is_command_type(&arg.ty) {
+                    self.command_bindings.extend(pat_ident(&arg.pat));
+                }
+                if is_config_type(&arg.ty) {
+                    self.config_bindings.extend(pat_ident(&arg.pat));
+                }
+            }
+        }
+
+        // Check if extern "C" function
+        if sig.abi.as_ref().is_some_and(|abi| abi.name.is_some()) {
+            self.in_extern_fn = true;
+        }
+        self.in_async = sig.asyncness.is_some();
+        self.in_runtime_main = false;
+        self.in_hot_fn = false;
+        self.in_iterator_next = false;
+        self.in_drop_impl = false;
+        self.in_pub_fn = false;
+        self.loop_depth = 0;
+        self.init_depth = 0;
+
+        outer
+    }
+
+    fn exit_fn(&mut self, outer: FnContext) {
+        self.in_extern_fn = outer.in_extern_fn;
+        self.in_async = outer.in_async;
+        self.in_runtime_main = outer.in_runtime_main;
+        self.in_hot_fn = outer.in_hot_fn;
+        self.in_iterator_next = outer.in_iterator_next;
+        self.in_drop_impl = outer.in_drop_impl;
+        self.in_pub_fn = outer.in_pub_fn;
+        self.loop_depth = outer.loop_depth;
+        self.init_depth = outer.init_depth;
+        self.iterator_adapter = outer.iterator_adapter;
+        self.in_parallel_adapter = outer.in_parallel_adapter;
+        self.after_unsafe_stmt = outer.after_unsafe_stmt;
+        self.current_fn = outer.current_fn;
+        self.map_bindings = outer.map_bindings;
+        self.value_bindings = outer.value_bindings;
+        self.command_bindings = outer.command_bindings;
+        self.untrusted_lengths = outer.untrusted_lengths;
+        self.stdin_buffers = outer.stdin_buffers;
+        self.config_bindings = outer.config_bindings;
+    }
+
+    /// Line of the node being checked, from its span. Synthetic tokens report
+    /// line 0; only for those fall back to searching the source for the
+    /// `quote!` output.
     fn find_line_in_source(&self, code_snippet: &str) -> usize {
+        match self.span_line() {
+            Some(line) => line,
+            None => self.locate_in_source(code_snippet).unwrap_or(1), // Default to line 1 if not found
+        }
+    }
+
+    fn span_line(&self) -> Option<usize> {
+        self.current_span.map(|span| span.start.line).filter(|&line| line > 0)
+    }
+
+    /// Line of the first source line containing the snippet, if any
+    fn locate_in_source(&self, code_snippet: &str) -> Option<usize> {
         // Remove whitespace and normalize the snippet for searching
         let normalized_snippet: String = code_snippet
             .chars()
@@ -38,7 +322,7 @@ impl Scanner {
             .collect();
         
         if normalized_snippet.is_empty() {
-            return 1;
+            return None;
         }
 
         // Search through source lines
@@ -49,117 +333,761 @@ impl Scanner {
                 .collect();
             
             if normalized_line.contains(&normalized_snippet) {
-                return line_num + 1; // Line numbers are 1-indexed
+                return Some(line_num + 1); // Line numbers are 1-indexed
             }
         }
         
-        1 // Default to line 1 if not found
+        None
+    }
+
+    /// `#[test]`/`#[bench]` fn or a file under `tests/`
+    fn in_test(&self) -> bool {
+        self.in_test_code || self.current_file.starts_with("tests/") || self.current_file.contains("/tests/")
+    }
+
+    /// Test code is out of scope unless `include_tests` asks for it
+    fn skips_test_code(&self) -> bool {
+        self.in_test() && !self.config.include_tests
+    }
+
+    /// Loop body, per-element closure, or a fn the user marked hot
+    fn in_hot_path(&self) -> bool {
+        self.loop_depth > 0 || self.iterator_adapter.is_some() || self.in_hot_fn
+    }
+
+    /// Record a finding with its surrounding context, emitting a diagnostic
+    /// event for `--log-level`
+    fn push(&mut self, mut vuln: Vulnerability) {
+        vuln.hot_path = self.in_hot_path();
+        vuln.in_async = self.in_async;
+        vuln.in_ffi = self.in_extern_fn;
+        vuln.in_runtime_main = self.in_runtime_main;
+        vuln.in_test = self.in_test();
+        vuln.span = self.current_span;
+        if let Some(span) = self.current_span.filter(|span| span.start.line > 0) {
+            vuln.column = span.start.column;
+        }
+
+        // Line 1 is also the lookup fallback: say so instead of pretending
+        if self.span_line().is_none() && vuln.line == 1 && self.locate_in_source(&vuln.code).is_none() {
+            tracing::warn!(file = %vuln.file, code = %vuln.code, "snippet not found in source; line unresolved");
+            vuln.line_unresolved = true;
+        }
+
+        if !vuln.line_unresolved && self.allowed_at(vuln.line, &vuln.rule_id) {
+            tracing::debug!(rule_id = %vuln.rule_id, line = vuln.line, "suppressed by panic-audit:allow");
+            self.suppressed.push(vuln);
+            return;
+        }
+
+        // Context decides, not the rule; later adjustments still apply on top
+        if self.config.exploitability.enabled {
+            let score = self.config.exploitability.score(&vuln);
+            vuln.severity = self.config.exploitability.severity(score);
+            vuln.exploitability = Some(score);
+        }
+
+        // A panic in a runtime entry fn stops the binary at startup, like in
+        // `main`; it can't take down a library caller
+        if self.in_runtime_main {
+            vuln.severity = vuln.severity.max(Severity::Medium);
+        }
+
+        // Drop runs during unwinding too, and a second panic there aborts
+        if self.in_drop_impl && DROP_PANIC_RULES.contains(&vuln.rule_id.as_str()) {
+            vuln.severity = Severity::Critical;
+            vuln.panic_class = PanicClass::PanicAmplification;
+            vuln.pattern = DROP_PATTERN.to_string();
+            vuln.detection_kind = DetectionKind::Contextual;
+        }
+
+        // Name-based opt-out from config: unwraps in trusted helpers are benign
+        if TRUSTED_FN_RULES.contains(&vuln.rule_id.as_str()) {
+            if let Some(name) = self.current_fn.as_deref().filter(|n| self.config.is_trusted_fn(n)) {
+                tracing::debug!(rule_id = %vuln.rule_id, function = name, "downgraded in trusted fn");
+                vuln.severity = Severity::Low;
+                vuln.pattern = format!("{} (in trusted fn `{}`)", vuln.pattern, name);
+            }
+        }
+
+        // Opt-in heuristic: the author vouched for the invariant in unsafe code
+        if self.after_unsafe_stmt
+            && self.config.trust_unsafe_invariants
+            && UNSAFE_INVARIANT_RULES.contains(&vuln.rule_id.as_str())
+        {
+            tracing::debug!(rule_id = %vuln.rule_id, "downgraded after unsafe invariant");
+            vuln.severity = Severity::Low;
+            vuln.pattern = format!("{} (after unsafe invariant)", vuln.pattern);
+            vuln.detection_kind = DetectionKind::Contextual;
+        }
+
+        if let Some(severity) = self.config.severity.get(&vuln.rule_id) {
+            vuln.severity = severity.clone();
+        }
+
+        tracing::info!(
+            rule_id = %vuln.rule_id,
+            severity = ?vuln.severity,
+            line = %vuln.line,
+            reason = %vuln.pattern,
+            "finding"
+        );
+        self.vulnerabilities.push(vuln);
+    }
+
+    /// A `// panic-audit:allow <rule ids>` comment naming `rule_id` at the
+    /// end of `line`, or on its own on the line above
+    fn allowed_at(&self, line: usize, rule_id: &str) -> bool {
+        if !self.has_allow_comments || line == 0 {
+            return false;
+        }
+        let mut lines = self.current_source.lines().skip(line.saturating_sub(2));
+        let above = if line > 1 { lines.next() } else { None };
+        let above = above.filter(|text| text.trim_start().starts_with("//"));
+        [lines.next(), above]
+            .into_iter()
+            .flatten()
+            .any(|text| rules::allow_comment_covers(text, rule_id))
     }
 
-    pub fn check_assumption_panic(&mut self, code: &str, _method: &str, line: usize) {
+    pub fn check_assumption_panic(&mut self, code: &str, method: &str, line: usize) {
+        let rule_id = if method == "expect" {
+            rules::RULE_EXPECT.id
+        } else {
+            rules::RULE_UNWRAP.id
+        };
+
         if is_false_positive(code) {
+            tracing::debug!(rule_id, line, code, "filtered false positive");
             return;
         }
 
-        let (severity, panic_class, pattern) = classify_panic(code);
-        
-        self.vulnerabilities.push(Vulnerability::new(
+        let (mut severity, mut panic_class, mut pattern, mut kind) = if self.runs_command_binding(code) {
+            rules::subprocess_panic(DetectionKind::Contextual)
+        } else {
+            classify_panic(code)
+        };
+        if pattern == rules::PATTERN_COMPILE {
+            (severity, pattern) = self.pattern_compile_context(code);
+            kind = DetectionKind::Contextual;
+        } else if self.parses_stdin_buffer(code) {
+            (severity, pattern, kind) =
+                (Severity::Medium, rules::PATTERN_STDIN.to_string(), DetectionKind::Contextual);
+        } else if severity > Severity::High && self.uses_sqlite && rules::is_sqlite_op(&normalize_code(code)) {
+            (severity, panic_class, pattern, kind) = (
+                Severity::High,
+                PanicClass::AssumptionPanic,
+                rules::PATTERN_SQLITE.to_string(),
+                DetectionKind::Contextual,
+            );
+        } else if severity > Severity::Medium && self.uses_tokio_sync && rules::is_try_lock(&normalize_code(code)) {
+            (severity, panic_class, pattern, kind) = (
+                Severity::Medium,
+                PanicClass::PanicAmplification,
+                rules::PATTERN_ASYNC_TRY_LOCK.to_string(),
+                DetectionKind::Contextual,
+            );
+        } else if severity > Severity::High &&
+            rules::is_untrusted_file_parse(&normalize_code(code), &self.config.untrusted_parsers)
+        {
+            (severity, panic_class, pattern, kind) = (
+                Severity::High,
+                PanicClass::CloudflareClass,
+                rules::PATTERN_FILE_PARSE.to_string(),
+                DetectionKind::Literal,
+            );
+        } else if severity != Severity::Critical && self.unwraps_config_element(code) {
+            (severity, panic_class, pattern, kind) = (
+                Severity::Critical,
+                PanicClass::CloudflareClass,
+                CONFIG_BOUNDS_PATTERN.to_string(),
+                DetectionKind::Contextual,
+            );
+        }
+        self.record_panic_site(line, rule_id, severity, panic_class, pattern, kind, code);
+    }
+
+    /// `cmd.output().unwrap()` where `cmd` was built by `Command::new`
+    fn runs_command_binding(&self, code: &str) -> bool {
+        let lower = normalize_code(code);
+        let receiver = lower.split('.').next().unwrap_or_default();
+        rules::runs_command(&lower) &&
+            self.command_bindings.iter().any(|binding| binding.to_lowercase() == receiver)
+    }
+
+    /// Severity of an unwrapped regex/glob compile by where it runs: a
+    /// one-time init or literal pattern fails deterministically at startup,
+    /// a dynamic pattern rebuilt every iteration is a crash and a perf problem
+    fn pattern_compile_context(&self, code: &str) -> (Severity, String) {
+        let lower = normalize_code(code);
+        let literal = rules::unwrapped_pattern_compile_arg(&lower)
+            .is_some_and(rules::is_literal_pattern);
+        let hot = self.in_hot_path();
+
+        if self.init_depth > 0 {
+            (Severity::Low, "Unwrapped pattern compile in one-time init".to_string())
+        } else if literal && hot {
+            (Severity::Medium, "Literal pattern recompiled per iteration (hoist into a Lazy)".to_string())
+        } else if literal {
+            (Severity::Low, "Unwrapped literal pattern compile".to_string())
+        } else if hot {
+            (Severity::High, "Dynamic pattern compiled per iteration (crash + perf)".to_string())
+        } else {
+            (Severity::Medium, rules::PATTERN_COMPILE.to_string())
+        }
+    }
+
+    /// `buf.trim().parse().unwrap()` where `buf` was read from stdin
+    fn parses_stdin_buffer(&self, code: &str) -> bool {
+        let lower = normalize_code(code);
+        lower.contains(".parse") &&
+            self.stdin_buffers.iter().any(|buf| lower.starts_with(&format!("{}.", buf.to_lowercase())))
+    }
+
+    /// `features.get(i).unwrap()` / `cfg.rules.first().unwrap()` where the
+    /// receiver came from a loaded config
+    fn unwraps_config_element(&self, code: &str) -> bool {
+        let lower = normalize_code(code);
+        let element = [".get(", ".first(", ".last(", ".nth("]
+            .iter()
+            .any(|call| lower.contains(call));
+        element && self.config_bindings.iter().any(|local| lower.starts_with(&format!("{}.", local.to_lowercase())))
+    }
+
+    /// `features[index]` where the slice or the index came from a loaded config
+    fn indexes_config_data(&self, node: &ExprIndex) -> bool {
+        [&*node.expr, &*node.index]
+            .into_iter()
+            .flat_map(|expr| ExprNames::of(expr).locals)
+            .any(|local| self.config_bindings.contains(&local))
+    }
+
+    /// Class 4: an allocation whose size reads a local derived from untrusted
+    /// input -- the length-prefix bug behind the Cloudflare outage
+    fn check_untrusted_alloc<'e>(&mut self, sizes: impl Iterator<Item = &'e Expr>, code: &str) {
+        if self.skips_test_code() {
+            return;
+        }
+
+        let tainted = sizes
+            .flat_map(|size| ExprNames::of(size).locals)
+            .find(|local| self.untrusted_lengths.contains(local));
+        let Some(local) = tainted else {
+            return;
+        };
+
+        let line = self.find_line_in_source(code);
+        tracing::debug!(line, local = %local, "allocation sized by untrusted length");
+        self.push(Vulnerability {
+            detection_kind: DetectionKind::Contextual,
+            ..Vulnerability::new(
+                self.current_file.clone(),
+                line,
+                rules::RULE_UNTRUSTED_ALLOC.id,
+                Severity::Critical,
+                PanicClass::CloudflareClass,
+                "Allocation sized by untrusted length field".to_string(),
+                snippet(code),
+            )
+        });
+    }
+
+    /// A length/capacity argument like `total - consumed`: when the right side
+    /// is larger the `usize` subtraction panics (or wraps to a huge size)
+    fn check_size_underflow(&mut self, size: Option<&Expr>, code: &str) {
+        if self.skips_test_code() || !size.is_some_and(has_runtime_sub) {
+            return;
+        }
+
+        let line = self.find_line_in_source(code);
+        self.push(Vulnerability::new(
             self.current_file.clone(),
-            line.to_string(),
-            severity,
-            panic_class,
-            pattern,
-            code.chars().take(120).collect(),
+            line,
+            rules::RULE_SIZE_UNDERFLOW.id,
+            Severity::High,
+            PanicClass::AllocationPanic,
+            "Allocation/size with possible usize underflow".to_string(),
+            snippet(code),
         ));
     }
 
+    /// `map[&key]` rather than `slice[i]`: the receiver is a binding known to
+    /// hold a map, or is named like one (`config_map`, `self.cache_map`)
+    fn is_map_index(&self, node: &ExprIndex) -> bool {
+        let Some(name) = receiver_name(&node.expr) else {
+            return false;
+        };
+        self.map_bindings.contains(&name) || is_map_name(&name)
+    }
+
+    /// Push an unwrap/expect/indexing finding, annotated with the iterator
+    /// adapter closure it sits in (if any)
+    #[allow(clippy::too_many_arguments)]
+    fn record_panic_site(
+        &mut self,
+        line: usize,
+        rule_id: &str,
+        severity: Severity,
+        panic_class: PanicClass,
+        pattern: String,
+        kind: DetectionKind,
+        code: &str,
+    ) {
+        let mut vuln = Vulnerability {
+            detection_kind: kind,
+            ..Vulnerability::new(
+                self.current_file.clone(),
+                line,
+                rule_id,
+                severity,
+                panic_class,
+                pattern,
+                snippet(code),
+            )
+        };
+
+        if self.in_iterator_next {
+            // Every `for x in it` / adapter chain over this type hits it,
+            // far from the code that actually panics
+            if vuln.severity != Severity::Critical {
+                vuln.severity = Severity::High;
+                vuln.pattern = ITERATOR_NEXT_PATTERN.to_string();
+                vuln.detection_kind = DetectionKind::Contextual;
+            }
+            vuln.iterator_adapter = self.iterator_adapter.clone();
+        } else if let Some(adapter) = &self.iterator_adapter {
+            // A more specific Critical classification stays as-is
+            if vuln.severity != Severity::Critical && self.in_parallel_adapter {
+                vuln.severity = Severity::High;
+                vuln.pattern = PARALLEL_ADAPTER_PATTERN.to_string();
+                vuln.detection_kind = DetectionKind::Contextual;
+            } else if vuln.severity != Severity::Critical {
+                vuln.severity = vuln.severity.min(Severity::Medium);
+                vuln.pattern = ADAPTER_PATTERN.to_string();
+                vuln.detection_kind = DetectionKind::Contextual;
+            }
+            vuln.iterator_adapter = Some(adapter.clone());
+        } else if self.in_hot_fn || self.loop_depth > 0 {
+            // A loop body or a fn the user marked hot runs far more often
+            // than the code around it
+            vuln.severity = vuln.severity.min(Severity::Medium);
+        }
+
+        self.push(vuln);
+    }
+
     pub fn check_panic_amplification(&mut self, code: &str, line: usize) {
-        let lower = code.to_lowercase();
+        let lower = normalize_code(code);
         
         // Class 3: Mutex/RwLock unwrap (panic amplification)
         if (lower.contains("mutex") || lower.contains("rwlock")) &&
            (lower.contains("lock(") || lower.contains("read(") || lower.contains("write(")) {
             
-            self.vulnerabilities.push(Vulnerability::new(
-                self.current_file.clone(),
-                line.to_string(),
-                Severity::Critical,
-                PanicClass::PanicAmplification,
-                "Mutex/RwLock unwrap (panic amplification)".to_string(),
-                code.chars().take(120).collect(),
-            ));
+            self.push(Vulnerability {
+                detection_kind: DetectionKind::Heuristic,
+                ..Vulnerability::new(
+                    self.current_file.clone(),
+                    line,
+                    rules::RULE_MUTEX_UNWRAP.id,
+                    Severity::Critical,
+                    PanicClass::PanicAmplification,
+                    "Mutex/RwLock unwrap (panic amplification)".to_string(),
+                    snippet(code),
+                )
+            });
+        }
+    }
+
+    /// `value["key"]` or `value["items"][0]` on a binding known to hold a
+    /// `serde_json::Value`, whose index yields Null instead of panicking. A
+    /// string key alone proves nothing: a `HashMap` index panics on it.
+    fn is_json_value_index(&self, node: &ExprIndex) -> bool {
+        let mut base = &*node.expr;
+        while let Expr::Index(inner) = base {
+            base = &inner.expr;
         }
+        receiver_name(base).is_some_and(|name| self.value_bindings.contains(&name))
     }
 }
 
 impl<'ast> Visit<'ast> for Scanner {
+    fn visit_file(&mut self, node: &'ast File) {
+        // `use` declarations don't carry over from the previous file
+        self.json_value_in_scope = false;
+        syn::visit::visit_file(self, node);
+    }
+
+    fn visit_item_use(&mut self, node: &'ast ItemUse) {
+        if imports_json_value(&node.tree, false) {
+            self.json_value_in_scope = true;
+        }
+        syn::visit::visit_item_use(self, node);
+    }
+
     fn visit_item_fn(&mut self, node: &'ast ItemFn) {
         let was_in_test = self.in_test_code;
-        let was_in_extern = self.in_extern_fn;
+        let outer = self.enter_fn(&node.sig);
         
-        // Check if test function
-        self.in_test_code = node.attrs.iter().any(|attr| {
+        // Check if test function; fns in a `#[cfg(test)]` mod stay test code
+        self.in_test_code |= node.attrs.iter().any(|attr| {
             if let Some(ident) = attr.path().get_ident() {
                 matches!(ident.to_string().as_str(), "test" | "bench")
             } else {
                 false
             }
         });
+        self.in_runtime_main = node.attrs.iter().any(is_runtime_main_attr);
+        self.in_hot_fn = node.attrs.iter().any(is_hot_path_attr);
+        self.in_pub_fn = matches!(node.vis, Visibility::Public(_));
 
-        // Check if extern "C" function
-        if let Some(abi) = &node.sig.abi {
-            if abi.name.is_some() {
-                self.in_extern_fn = true;
-            }
+        syn::visit::visit_item_fn(self, node);
+        self.in_test_code = was_in_test;
+        self.exit_fn(outer);
+    }
+
+    fn visit_block(&mut self, node: &'ast Block) {
+        let outer = self.after_unsafe_stmt;
+        let mut previous_unsafe = false;
+        for stmt in &node.stmts {
+            self.after_unsafe_stmt = previous_unsafe;
+            self.visit_stmt(stmt);
+            previous_unsafe = is_unsafe_stmt(stmt);
         }
+        self.after_unsafe_stmt = outer;
+    }
 
-        syn::visit::visit_item_fn(self, node);
+    fn visit_item_mod(&mut self, node: &'ast ItemMod) {
+        let was_in_test = self.in_test_code;
+        self.in_test_code |= node.attrs.iter().any(is_cfg_test_attr);
+        syn::visit::visit_item_mod(self, node);
         self.in_test_code = was_in_test;
-        self.in_extern_fn = was_in_extern;
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast ItemImpl) {
+        let trait_name = node
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string());
+        let outer = std::mem::replace(&mut self.impl_trait, trait_name);
+        syn::visit::visit_item_impl(self, node);
+        self.impl_trait = outer;
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        let outer = self.enter_fn(&node.sig);
+        self.in_hot_fn = node.attrs.iter().any(is_hot_path_attr);
+        self.in_iterator_next = node.sig.ident == "next" && self.impl_trait.as_deref() == Some("Iterator");
+        self.in_drop_impl = node.sig.ident == "drop" && self.impl_trait.as_deref() == Some("Drop");
+        self.in_pub_fn = matches!(node.vis, Visibility::Public(_));
+        syn::visit::visit_impl_item_fn(self, node);
+        self.exit_fn(outer);
+    }
+
+    fn visit_expr_async(&mut self, node: &'ast ExprAsync) {
+        let was_async = self.in_async;
+        self.in_async = true;
+        syn::visit::visit_expr_async(self, node);
+        self.in_async = was_async;
+    }
+
+    fn visit_expr_for_loop(&mut self, node: &'ast ExprForLoop) {
+        let after_unsafe = std::mem::take(&mut self.after_unsafe_stmt);
+        // The iterator expression runs once; only the body is hot
+        self.visit_pat(&node.pat);
+        self.visit_expr(&node.expr);
+        self.loop_depth += 1;
+        self.visit_block(&node.body);
+        self.loop_depth -= 1;
+        self.after_unsafe_stmt = after_unsafe;
+    }
+
+    fn visit_expr_while(&mut self, node: &'ast ExprWhile) {
+        let after_unsafe = std::mem::take(&mut self.after_unsafe_stmt);
+        self.loop_depth += 1;
+        syn::visit::visit_expr_while(self, node);
+        self.loop_depth -= 1;
+        self.after_unsafe_stmt = after_unsafe;
+    }
+
+    fn visit_expr_loop(&mut self, node: &'ast ExprLoop) {
+        let after_unsafe = std::mem::take(&mut self.after_unsafe_stmt);
+        self.loop_depth += 1;
+        syn::visit::visit_expr_loop(self, node);
+        self.loop_depth -= 1;
+        self.after_unsafe_stmt = after_unsafe;
+    }
+
+    fn visit_expr_closure(&mut self, node: &'ast ExprClosure) {
+        // A closure may run long after the unsafe statement, or many times
+        let after_unsafe = std::mem::take(&mut self.after_unsafe_stmt);
+        syn::visit::visit_expr_closure(self, node);
+        self.after_unsafe_stmt = after_unsafe;
     }
 
     fn visit_expr_method_call(&mut self, node: &'ast ExprMethodCall) {
         let method = node.method.to_string();
+        self.current_span = Some(method_call_span(node));
 
-        if !self.in_test_code && !self.current_file.contains("/tests/") {
+        let merged = self.merged_unwrap.is_some_and(|span| Some(span) == self.current_span);
+        if !self.skips_test_code() && !merged {
             let code = quote!(#node).to_string();
             let line = self.find_line_in_source(&code);
+            let compound = matches!(method.as_str(), "unwrap" | "expect")
+                .then(|| compound_lock_unwrap(node))
+                .flatten();
 
-            // Class 1: Assumption panics
-            if matches!(method.as_str(), "unwrap" | "expect" | "unwrap_unchecked") {
-                self.check_assumption_panic(&code, &method, line);
+            if let Some(guard) = compound {
+                // Class 3: a poisoned lock and a missing key, reported once
+                self.merged_unwrap = Some(method_call_span(guard));
+                self.record_panic_site(
+                    line,
+                    rules::RULE_MUTEX_UNWRAP.id,
+                    Severity::Critical,
+                    PanicClass::PanicAmplification,
+                    COMPOUND_LOCK_PATTERN.to_string(),
+                    DetectionKind::Literal,
+                    &code,
+                );
+            } else {
+                if let Some(open) = matches!(method.as_str(), "unwrap" | "expect")
+                    .then(|| reader_over_open_unwrap(node))
+                    .flatten()
+                {
+                    // Class 4: one finding names both failures, on the outer unwrap
+                    self.merged_unwrap = Some(method_call_span(open));
+                }
+
+                // Class 1: Assumption panics
+                if matches!(method.as_str(), "unwrap" | "expect" | "unwrap_unchecked") {
+                    self.check_assumption_panic(&code, &method, line);
+                }
+
+                // Class 3: Panic amplification (Mutex/RwLock unwrap)
+                if method == "unwrap" || method == "expect" {
+                    self.check_panic_amplification(&code, line);
+                }
             }
 
-            // Class 3: Panic amplification (Mutex/RwLock unwrap)
-            if method == "unwrap" || method == "expect" {
-                self.check_panic_amplification(&code, line);
+            // Class 4: binary protocol reads with no length check, no unwrap needed
+            if rules::is_buf_read(&method) && is_buf_receiver(&node.receiver) {
+                self.record_panic_site(
+                    line,
+                    rules::RULE_BUF_READ.id,
+                    Severity::Critical,
+                    PanicClass::CloudflareClass,
+                    "Unchecked bytes::Buf read (insufficient bytes panic)".to_string(),
+                    DetectionKind::Heuristic,
+                    &code,
+                );
+            }
+
+            if method == "unwrap_or_else" && rules::is_poison_ignored(&normalize_code(&code)) {
+                self.push(Vulnerability::new(
+                    self.current_file.clone(),
+                    line,
+                    rules::RULE_POISON_IGNORED.id,
+                    Severity::Medium,
+                    PanicClass::PanicAmplification,
+                    "Ignoring lock poisoning (operates on possibly-corrupt state)".to_string(),
+                    snippet(&code),
+                ));
+            }
+        }
+
+        if matches!(method.as_str(), "read_line" | "read_to_string") {
+            let receiver = &node.receiver;
+            if normalize_code(&quote!(#receiver).to_string()).contains("stdin") {
+                for arg in &node.args {
+                    self.stdin_buffers.extend(ExprNames::of(arg).locals);
+                }
             }
         }
 
+        if matches!(method.as_str(), "reserve" | "reserve_exact") {
+            self.check_untrusted_alloc(node.args.iter(), &quote!(#node).to_string());
+        }
+
+        if SIZE_METHODS.contains(&method.as_str()) {
+            self.check_size_underflow(node.args.first(), &quote!(#node).to_string());
+        }
+
+        if INIT_METHODS.contains(&method.as_str()) {
+            self.visit_expr(&node.receiver);
+            self.init_depth += 1;
+            for arg in &node.args {
+                self.visit_expr(arg);
+            }
+            self.init_depth -= 1;
+            return;
+        }
+
+        if ITERATOR_ADAPTERS.contains(&method.as_str()) && is_iterator_chain(&node.receiver) {
+            self.visit_expr(&node.receiver);
+            // Sequential iterators inside a parallel closure still run on the pool
+            let parallel = self.in_parallel_adapter || is_parallel_chain(&node.receiver);
+            for arg in &node.args {
+                if let Expr::Closure(closure) = arg {
+                    let outer = self.iterator_adapter.replace(method.clone());
+                    let outer_parallel = std::mem::replace(&mut self.in_parallel_adapter, parallel);
+                    self.visit_expr_closure(closure);
+                    self.iterator_adapter = outer;
+                    self.in_parallel_adapter = outer_parallel;
+                } else {
+                    self.visit_expr(arg);
+                }
+            }
+            return;
+        }
+
         syn::visit::visit_expr_method_call(self, node);
     }
 
+    fn visit_expr_call(&mut self, node: &'ast ExprCall) {
+        self.current_span = Some(source_span(node));
+
+        // Vec::with_capacity(n), String::with_capacity(n), ...
+        if let Expr::Path(path) = &*node.func {
+            if path.path.segments.last().is_some_and(|s| s.ident == "with_capacity") {
+                self.check_untrusted_alloc(node.args.iter(), &quote!(#node).to_string());
+                self.check_size_underflow(node.args.first(), &quote!(#node).to_string());
+            }
+        }
+
+        if is_lazy_new(&node.func) {
+            self.visit_expr(&node.func);
+            self.init_depth += 1;
+            for arg in &node.args {
+                self.visit_expr(arg);
+            }
+            self.init_depth -= 1;
+            return;
+        }
+
+        syn::visit::visit_expr_call(self, node);
+    }
+
+    fn visit_local(&mut self, node: &'ast Local) {
+        let typed_map = matches!(&node.pat, Pat::Type(typed) if is_map_type(&typed.ty));
+        let init_map = node.init.as_ref().is_some_and(|init| {
+            let init = &init.expr;
+            let code = quote!(#init).to_string();
+            MAP_TYPES.iter().any(|ty| code.contains(ty))
+        });
+        if typed_map || init_map {
+            self.map_bindings.extend(pat_ident(&node.pat));
+        }
+
+        // A declared type wins: `let cfg: Config = serde_json::from_str(..)` is no Value
+        let json_value = match &node.pat {
+            Pat::Type(typed) => is_json_value_type(&typed.ty, self.json_value_in_scope),
+            _ => node.init.as_ref().is_some_and(|init| {
+                let init = &init.expr;
+                is_json_value_source(&normalize_code(&quote!(#init).to_string()))
+            }),
+        };
+        if json_value {
+            self.value_bindings.extend(pat_ident(&node.pat));
+        }
+
+        let command = match &node.pat {
+            Pat::Type(typed) => is_command_type(&typed.ty),
+            _ => node.init.as_ref().is_some_and(|init| is_command_new(&init.expr)),
+        };
+        if command {
+            self.command_bindings.extend(pat_ident(&node.pat));
+        }
+
+        // Config values and anything computed from them; the binding's name
+        // and type count (`let cfg: Settings = toml::from_str(..)`)
+        if let Some(init) = &node.init {
+            let from_config = rules::is_config_load(&normalize_code(&quote!(#node).to_string())) ||
+                ExprNames::of(&init.expr).locals.iter().any(|local| self.config_bindings.contains(local));
+            if from_config {
+                self.config_bindings.extend(pat_idents(&node.pat));
+            }
+        }
+
+        // Light dataflow for PA010: `let n = read_len(&buf);`, `let m = n * 2;`
+        if let Some(init) = &node.init {
+            let reads = ExprNames::of(&init.expr);
+            let untrusted = reads.calls.iter().any(|call| is_untrusted_source(call)) ||
+                reads.locals.iter().any(|local| self.untrusted_lengths.contains(local));
+            if untrusted {
+                self.untrusted_lengths.extend(pat_idents(&node.pat));
+            }
+        }
+
+        syn::visit::visit_local(self, node);
+    }
+
     fn visit_expr_index(&mut self, node: &'ast ExprIndex) {
-        if !self.in_test_code && !self.current_file.contains("/tests/") {
+        self.current_span = Some(source_span(node));
+        if !self.skips_test_code() {
             // Class 2: Implicit panics (indexing)
             let code = quote!(#node).to_string();
             let line = self.find_line_in_source(&code);
-            
-            self.vulnerabilities.push(Vulnerability::new(
-                self.current_file.clone(),
-                line.to_string(),
-                Severity::Medium,
-                PanicClass::ImplicitPanic,
-                "Array/Slice Indexing".to_string(),
-                code.chars().take(120).collect(),
-            ));
+
+            if self.indexes_config_data(node) && !self.is_json_value_index(node) {
+                // Class 4: the config grew (or shrank) past what the code assumed
+                self.record_panic_site(
+                    line,
+                    rules::RULE_INDEXING.id,
+                    Severity::Critical,
+                    PanicClass::CloudflareClass,
+                    CONFIG_BOUNDS_PATTERN.to_string(),
+                    DetectionKind::Contextual,
+                    &code,
+                );
+            } else if self.is_map_index(node) {
+                let kind = if receiver_name(&node.expr).is_some_and(|name| self.map_bindings.contains(&name)) {
+                    DetectionKind::Contextual
+                } else {
+                    DetectionKind::Heuristic
+                };
+                self.record_panic_site(
+                    line,
+                    rules::RULE_INDEXING.id,
+                    Severity::High,
+                    PanicClass::ImplicitPanic,
+                    "Map index [] (missing key panic)".to_string(),
+                    kind,
+                    &code,
+                );
+            } else if self.is_json_value_index(node) {
+                // serde_json::Value indexing yields Null instead of panicking;
+                // the panic comes from the accessor unwrap that usually follows
+                self.push(Vulnerability {
+                    detection_kind: DetectionKind::Heuristic,
+                    ..Vulnerability::new(
+                        self.current_file.clone(),
+                        line,
+                        rules::RULE_INDEXING.id,
+                        Severity::Low,
+                        PanicClass::ImplicitPanic,
+                        "serde_json::Value index (Null on missing key)".to_string(),
+                        snippet(&code),
+                    )
+                });
+            } else {
+                self.record_panic_site(
+                    line,
+                    rules::RULE_INDEXING.id,
+                    Severity::Medium,
+                    PanicClass::ImplicitPanic,
+                    "Array/Slice Indexing".to_string(),
+                    DetectionKind::Literal,
+                    &code,
+                );
+            }
         }
 
         syn::visit::visit_expr_index(self, node);
     }
 
     fn visit_macro(&mut self, node: &'ast Macro) {
-        if !self.in_test_code && !self.current_file.contains("/tests/") {
+        self.current_span = Some(source_span(node));
+        if !self.skips_test_code() {
             let macro_name = node.path.segments.last()
                 .map(|s| s.ident.to_string())
                 .unwrap_or_default();
@@ -168,39 +1096,77 @@ impl<'ast> Visit<'ast> for Scanner {
             let line = self.find_line_in_source(&code);
 
             match macro_name.as_str() {
+                // vec![0; n]
+                "vec" => {
+                    let tokens = &node.tokens;
+                    if let Ok(repeat) = syn::parse2::<ExprRepeat>(quote!([#tokens])) {
+                        self.check_untrusted_alloc(std::iter::once(&*repeat.len), &code);
+                    }
+                }
+
                 // Class 2: Implicit panics
+                "panic" => {
+                    self.push(Vulnerability::new(
+                        self.current_file.clone(),
+                        line,
+                        rules::RULE_PANIC.id,
+                        Severity::Critical,
+                        PanicClass::ImplicitPanic,
+                        "panic!()".to_string(),
+                        snippet(&code),
+                    ));
+                }
+
+                // Reachable after all if an invariant breaks; worse when
+                // callers outside the crate can drive the fn there
+                "unreachable" => {
+                    let severity = if self.in_pub_fn { Severity::High } else { Severity::Medium };
+                    self.push(Vulnerability::new(
+                        self.current_file.clone(),
+                        line,
+                        rules::RULE_UNREACHABLE.id,
+                        severity,
+                        PanicClass::ImplicitPanic,
+                        "unreachable!()".to_string(),
+                        snippet(&code),
+                    ));
+                }
+
                 "todo" | "unimplemented" => {
-                    self.vulnerabilities.push(Vulnerability::new(
+                    self.push(Vulnerability::new(
                         self.current_file.clone(),
-                        line.to_string(),
+                        line,
+                        rules::RULE_TODO.id,
                         Severity::Critical,
                         PanicClass::ImplicitPanic,
                         format!("{}!()", macro_name),
-                        code.chars().take(120).collect(),
+                        snippet(&code),
                     ));
                 }
                 
                 // Class 5: Assertion failures
                 "assert" | "assert_eq" | "assert_ne" | "debug_assert" => {
-                    self.vulnerabilities.push(Vulnerability::new(
+                    self.push(Vulnerability::new(
                         self.current_file.clone(),
-                        line.to_string(),
+                        line,
+                        rules::RULE_ASSERTION.id,
                         Severity::Medium,
                         PanicClass::AssertionFailure,
                         format!("{}!()", macro_name),
-                        code.chars().take(120).collect(),
+                        snippet(&code),
                     ));
                 }
 
                 // Class 8: Process-killing
                 "exit" if code.contains("std::process") => {
-                    self.vulnerabilities.push(Vulnerability::new(
+                    self.push(Vulnerability::new(
                         self.current_file.clone(),
-                        line.to_string(),
+                        line,
+                        rules::RULE_PROCESS_EXIT.id,
                         Severity::Critical,
                         PanicClass::ProcessKilling,
                         "process::exit()".to_string(),
-                        code.chars().take(120).collect(),
+                        snippet(&code),
                     ));
                 }
                 
@@ -210,4 +1176,399 @@ impl<'ast> Visit<'ast> for Scanner {
 
         syn::visit::visit_macro(self, node);
     }
-}
\ No newline at end of file
+}
+
+/// Finding code as shown in reports: the first `SNIPPET_CHARS` characters.
+/// Truncate by `char`, never by byte offset, so non-ASCII code can't split a
+/// character.
+fn snippet(code: &str) -> String {
+    code.chars().take(SNIPPET_CHARS).collect()
+}
+
+/// `serde_json::Value` or `&serde_json::Value`; a bare `Value` only where
+/// `use serde_json::Value` brought it into scope (`toml::Value` is no JSON)
+fn is_json_value_type(ty: &Type, value_in_scope: bool) -> bool {
+    match ty {
+        Type::Reference(reference) => is_json_value_type(&reference.elem, value_in_scope),
+        Type::Path(path) => {
+            let segments: Vec<String> = path.path.segments.iter().map(|s| s.ident.to_string()).collect();
+            match segments.as_slice() {
+                [name] => name == "Value" && value_in_scope,
+                [.., krate, name] => krate == "serde_json" && name == "Value",
+                [] => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Whether a method-call receiver looks like an iterator pipeline rather than
+/// an `Option`/`Result` (both of which also have `map`)
+fn is_iterator_chain(expr: &Expr) -> bool {
+    match expr {
+        Expr::MethodCall(call) => {
+            let method = call.method.to_string();
+            ITERATOR_METHODS.contains(&method.as_str()) ||
+                PARALLEL_ITERATOR_METHODS.contains(&method.as_str()) ||
+                ITERATOR_ADAPTERS.contains(&method.as_str()) ||
+                is_iterator_chain(&call.receiver)
+        }
+        Expr::Paren(paren) => is_iterator_chain(&paren.expr),
+        _ => false,
+    }
+}
+
+/// An iterator pipeline started by a rayon `par_*` method
+fn is_parallel_chain(expr: &Expr) -> bool {
+    match expr {
+        Expr::MethodCall(call) => {
+            PARALLEL_ITERATOR_METHODS.contains(&call.method.to_string().as_str()) ||
+                is_parallel_chain(&call.receiver)
+        }
+        Expr::Paren(paren) => is_parallel_chain(&paren.expr),
+        _ => false,
+    }
+}
+
+/// 1-based start/end of a parsed node (needs proc-macro2 `span-locations`)
+fn source_span(node: &impl Spanned) -> SourceSpan {
+    let span = node.span();
+    span_between(span, span)
+}
+
+/// `method::<T>(args)` without its receiver, so a call at the end of a
+/// multi-line chain is placed on its own line rather than where the chain starts
+fn method_call_span(node: &ExprMethodCall) -> SourceSpan {
+    span_between(node.method.span(), node.paren_token.span.close())
+}
+
+fn span_between(first: proc_macro2::Span, last: proc_macro2::Span) -> SourceSpan {
+    let position = |at: proc_macro2::LineColumn| Position { line: at.line, column: at.column + 1 };
+    SourceSpan {
+        start: position(first.start()),
+        end: position(last.end()),
+    }
+}
+
+/// `#[tokio::main]`, `#[tokio::main(flavor = "current_thread")]`, `#[actix_web::main]`, ...
+fn is_runtime_main_attr(attr: &Attribute) -> bool {
+    let segments: Vec<_> = attr.path().segments.iter().map(|s| s.ident.to_string()).collect();
+    matches!(segments.as_slice(), [krate, main] if main == "main" && RUNTIME_MAIN_CRATES.contains(&krate.as_str()))
+}
+
+/// `#[panic_audit::hot_path]`, also inside `#[cfg_attr(..)]` so the crate
+/// still compiles without a `panic_audit` attribute in scope
+fn is_hot_path_attr(attr: &Attribute) -> bool {
+    normalize_code(&quote!(#attr).to_string()).contains("panic_audit::hot_path")
+}
+
+/// `unsafe { .. };` or `let x = unsafe { .. };`
+fn is_unsafe_stmt(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Expr(Expr::Unsafe(_), _) => true,
+        Stmt::Local(local) => local.init.as_ref().is_some_and(|init| matches!(&*init.expr, Expr::Unsafe(_))),
+        _ => false,
+    }
+}
+
+/// `#[cfg(test)]`, also within `all(..)`/`any(..)`, or a cfg on a test
+/// feature (`feature = "test-utils"`). Predicates under `not(..)` don't count.
+fn is_cfg_test_attr(attr: &Attribute) -> bool {
+    match &attr.meta {
+        Meta::List(list) if list.path.is_ident("cfg") => cfg_enables_test(list.tokens.clone()),
+        _ => false,
+    }
+}
+
+fn cfg_enables_test(tokens: TokenStream) -> bool {
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident == "not" => {
+                tokens.next();
+            }
+            TokenTree::Ident(ident) if ident == "test" => return true,
+            TokenTree::Literal(lit) if is_test_feature(&lit.to_string()) => return true,
+            TokenTree::Group(group) if cfg_enables_test(group.stream()) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// `"test"`, `"test-utils"`, `"integration_test"`: `test` as a whole
+/// `-`/`_`-separated word, so `"attestation"` or `"latest-api"` don't count
+fn is_test_feature(literal: &str) -> bool {
+    literal.trim_matches('"').split(['-', '_']).any(|word| word == "test")
+}
+
+/// `Lazy::new`, `once_cell::sync::Lazy::new`, `LazyLock::new`, ...
+fn is_lazy_new(func: &Expr) -> bool {
+    let Expr::Path(path) = func else {
+        return false;
+    };
+    let mut segments = path.path.segments.iter().rev();
+    let is_new = segments.next().is_some_and(|s| s.ident == "new");
+    is_new && segments.next().is_some_and(|s| LAZY_TYPES.iter().any(|t| s.ident == t))
+}
+
+/// `use serde_json::Value;`, `use serde_json::{json, Value};` or
+/// `use serde_json::*;`. A renamed import doesn't make `Value` the JSON type.
+fn imports_json_value(tree: &UseTree, in_serde_json: bool) -> bool {
+    match tree {
+        UseTree::Path(path) => imports_json_value(&path.tree, path.ident == "serde_json"),
+        UseTree::Name(name) => in_serde_json && name.ident == "Value",
+        UseTree::Glob(_) => in_serde_json,
+        UseTree::Group(group) => group.items.iter().any(|item| imports_json_value(item, in_serde_json)),
+        UseTree::Rename(_) => false,
+    }
+}
+
+/// `serde_json::from_str(..)`, `serde_json::to_value(..)`, `json!({..})`.
+/// Expects normalized code.
+fn is_json_value_source(init: &str) -> bool {
+    init.starts_with("serde_json::from_") ||
+        init.starts_with("serde_json::to_value") ||
+        init.starts_with("json!") ||
+        init.starts_with("serde_json::json!")
+}
+
+/// `Command`, `&mut Command` or a `process::Command` path (`clap::Command`
+/// builds no subprocess)
+fn is_command_type(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) => is_command_type(&reference.elem),
+        Type::Path(path) => {
+            let segments: Vec<String> = path.path.segments.iter().map(|s| s.ident.to_string()).collect();
+            match segments.as_slice() {
+                [name] => name == "Command",
+                [.., module, name] => module == "process" && name == "Command",
+                [] => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// `Command::new(..)` or `std::process::Command::new(..)`
+fn is_command_new(expr: &Expr) -> bool {
+    let Expr::Call(call) = expr else {
+        return false;
+    };
+    let Expr::Path(func) = &*call.func else {
+        return false;
+    };
+    let segments: Vec<String> = func.path.segments.iter().map(|s| s.ident.to_string()).collect();
+    match segments.as_slice() {
+        [command, new] => command == "Command" && new == "new",
+        [.., module, command, new] => module == "process" && command == "Command" && new == "new",
+        _ => false,
+    }
+}
+
+/// `Config`, `&AppConfig`, `Arc<Settings>`
+fn is_config_type(ty: &Type) -> bool {
+    let ty = quote!(#ty).to_string();
+    ty.contains("Config") || ty.contains("Settings")
+}
+
+fn is_map_type(ty: &Type) -> bool {
+    let ty = quote!(#ty).to_string();
+    MAP_TYPES.iter().any(|map| ty.contains(map))
+}
+
+/// Every binding a pattern introduces: `n`, `(n, rest)`, `(n, _): (usize, _)`
+fn pat_idents(pat: &Pat) -> Vec<String> {
+    match pat {
+        Pat::Ident(ident) => vec![ident.ident.to_string()],
+        Pat::Type(typed) => pat_idents(&typed.pat),
+        Pat::Tuple(tuple) => tuple.elems.iter().flat_map(pat_idents).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Function/method names that read or decode external bytes
+fn is_untrusted_source(call: &str) -> bool {
+    ["read", "parse", "decode"].iter().any(|prefix| call.starts_with(prefix)) ||
+        (call.starts_with("get_") && rules::is_buf_read(call)) ||
+        ["_be_bytes", "_le_bytes", "_ne_bytes"].iter().any(|suffix| call.ends_with(suffix)) ||
+        call.contains("deserialize") ||
+        matches!(call, "from_slice" | "from_reader")
+}
+
+/// `a - b` anywhere in the expression with neither side a literal or an
+/// `ALL_CAPS` constant
+fn has_runtime_sub(expr: &Expr) -> bool {
+    struct Finder(bool);
+
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_expr_binary(&mut self, node: &'ast ExprBinary) {
+            if matches!(node.op, BinOp::Sub(_)) && !is_constant(&node.left) && !is_constant(&node.right) {
+                self.0 = true;
+            }
+            syn::visit::visit_expr_binary(self, node);
+        }
+    }
+
+    let mut finder = Finder(false);
+    finder.visit_expr(expr);
+    finder.0
+}
+
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lit(_) => true,
+        Expr::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            let name = segment.ident.to_string();
+            name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        }),
+        Expr::Paren(paren) => is_constant(&paren.expr),
+        _ => false,
+    }
+}
+
+/// Called function/method names and plain local variables in an expression
+#[derive(Default)]
+struct ExprNames {
+    calls: Vec<String>,
+    locals: Vec<String>,
+}
+
+impl ExprNames {
+    fn of(expr: &Expr) -> Self {
+        let mut names = Self::default();
+        names.visit_expr(expr);
+        names
+    }
+}
+
+impl<'ast> Visit<'ast> for ExprNames {
+    fn visit_expr_call(&mut self, node: &'ast ExprCall) {
+        if let Expr::Path(path) = &*node.func {
+            if let Some(segment) = path.path.segments.last() {
+                self.calls.push(segment.ident.to_string());
+            }
+        }
+        syn::visit::visit_expr_call(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast ExprMethodCall) {
+        self.calls.push(node.method.to_string());
+        syn::visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast ExprPath) {
+        if let Some(ident) = node.path.get_ident() {
+            self.locals.push(ident.to_string());
+        }
+    }
+}
+
+/// The binding name in `x`, `mut x` or `x: T`
+fn pat_ident(pat: &Pat) -> Option<String> {
+    match pat {
+        Pat::Ident(ident) => Some(ident.ident.to_string()),
+        Pat::Type(typed) => pat_ident(&typed.pat),
+        _ => None,
+    }
+}
+
+/// A `_`-separated word of the name is a map type (`map`, `id_map`,
+/// `by_name_hashmap`); `mmap`, `heatmap` or `roadmap` are not
+fn is_map_name(name: &str) -> bool {
+    name.to_lowercase()
+        .split('_')
+        .any(|word| matches!(word, "map" | "hashmap" | "btreemap" | "indexmap"))
+}
+
+/// Receivers named like a byte buffer (`buf`, `self.read_buf`, `bytes`);
+/// `Buf` is a trait, so the type is not visible at the call site
+fn is_buf_receiver(expr: &Expr) -> bool {
+    receiver_name(expr).is_some_and(|name| {
+        let name = name.to_lowercase();
+        name.contains("buf") || name.contains("bytes")
+    })
+}
+
+/// In `m.lock().unwrap().get_mut(&k).unwrap()`, the inner `m.lock().unwrap()`:
+/// an unwrapped lock guard whose collection lookup is itself unwrapped.
+/// Value adapters (`.copied()`, `.cloned()`) between lookup and unwrap are skipped.
+fn compound_lock_unwrap(node: &ExprMethodCall) -> Option<&ExprMethodCall> {
+    let mut lookup = match &*node.receiver {
+        Expr::MethodCall(call) => call,
+        _ => return None,
+    };
+    while LOOKUP_ADAPTERS.contains(&lookup.method.to_string().as_str()) {
+        lookup = match &*lookup.receiver {
+            Expr::MethodCall(call) => call,
+            _ => return None,
+        };
+    }
+    if !LOOKUP_METHODS.contains(&lookup.method.to_string().as_str()) {
+        return None;
+    }
+
+    let Expr::MethodCall(guard) = &*lookup.receiver else {
+        return None;
+    };
+    let Expr::MethodCall(lock) = &*guard.receiver else {
+        return None;
+    };
+    (matches!(guard.method.to_string().as_str(), "unwrap" | "expect") &&
+        matches!(lock.method.to_string().as_str(), "lock" | "read" | "write"))
+        .then_some(guard)
+}
+
+/// In `from_reader(File::open(p).unwrap()).unwrap()`, the inner
+/// `File::open(p).unwrap()`, also through `BufReader::new(..)`
+fn reader_over_open_unwrap(node: &ExprMethodCall) -> Option<&ExprMethodCall> {
+    let Expr::Call(reader) = &*node.receiver else {
+        return None;
+    };
+    let Expr::Path(func) = &*reader.func else {
+        return None;
+    };
+    let name = func.path.segments.last()?.ident.to_string();
+    if !matches!(name.as_str(), "from_reader" | "deserialize_from") {
+        return None;
+    }
+    reader.args.iter().find_map(open_unwrap)
+}
+
+fn open_unwrap(expr: &Expr) -> Option<&ExprMethodCall> {
+    match expr {
+        Expr::MethodCall(call) if matches!(call.method.to_string().as_str(), "unwrap" | "expect") => {
+            let Expr::Call(open) = &*call.receiver else {
+                return None;
+            };
+            let Expr::Path(func) = &*open.func else {
+                return None;
+            };
+            let mut segments = func.path.segments.iter().rev();
+            let is_open = segments.next().is_some_and(|s| s.ident == "open");
+            (is_open && segments.next().is_some_and(|s| s.ident == "File")).then_some(call)
+        }
+        // `BufReader::new(..)` and other wrappers
+        Expr::Call(wrapper) => wrapper.args.iter().find_map(open_unwrap),
+        Expr::Reference(reference) => open_unwrap(&reference.expr),
+        Expr::Paren(paren) => open_unwrap(&paren.expr),
+        _ => None,
+    }
+}
+
+/// Variable or field name an index expression is applied to
+fn receiver_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+        Expr::Field(field) => match &field.member {
+            Member::Named(ident) => Some(ident.to_string()),
+            Member::Unnamed(_) => None,
+        },
+        Expr::Reference(reference) => receiver_name(&reference.expr),
+        Expr::Paren(paren) => receiver_name(&paren.expr),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,1239 @@
+use super::*;
+use crate::audit;
+
+/// Findings for `source` scanned as `src/lib.rs` under `config`
+fn scan_with(config: Config, source: &str) -> Vec<Vulnerability> {
+    let mut scanner = Scanner::new("fixture".to_string(), config);
+    scanner.current_file = "src/lib.rs".to_string();
+    audit::scan_source(&mut scanner, source.to_string()).expect("fixture parses");
+    scanner.vulnerabilities
+}
+
+fn scan(source: &str) -> Vec<Vulnerability> {
+    scan_with(Config::default(), source)
+}
+
+/// The one finding reported for `key`, a rule id or a pattern
+fn only<'a>(findings: &'a [Vulnerability], key: &str) -> &'a Vulnerability {
+    let matching: Vec<_> = findings.iter().filter(|v| v.rule_id == key || v.pattern == key).collect();
+    assert_eq!(matching.len(), 1, "expected one {:?} finding in {:#?}", key, findings);
+    matching[0]
+}
+
+/// The outermost unwrap/expect finding for `expr` in a library fn body
+fn unwrap_finding(expr: &str) -> Vulnerability {
+    let findings = scan(&format!("pub fn f() {{ let _ = {}; }}", expr));
+    findings
+        .into_iter()
+        .find(|v| v.rule_id == rules::RULE_UNWRAP.id || v.rule_id == rules::RULE_EXPECT.id)
+        .unwrap_or_else(|| panic!("no unwrap finding for {}", expr))
+}
+
+const VALUE_INDEX: &str = "serde_json::Value index (Null on missing key)";
+
+#[test]
+fn json_value_accessor_unwrap_is_high() {
+    let findings = scan(r#"
+        fn name(v: &serde_json::Value) -> &str {
+            v["k"].as_str().unwrap()
+        }
+    "#);
+
+    let unwrap = only(&findings, "Unwrapped serde_json::Value accessor");
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+
+    let index = only(&findings, VALUE_INDEX);
+    assert_eq!(index.severity, Severity::Low);
+}
+
+#[test]
+fn json_value_index_needs_a_value_binding() {
+    let findings = scan(r#"
+        fn lookup(body: &str) -> u64 {
+            let v = serde_json::from_str(body).unwrap();
+            let counts: HashMap<String, u64> = HashMap::new();
+            let parsed = json!({ "a": 1 });
+            v["a"].as_u64().unwrap_or(0) + counts["x"] + parsed["a"].as_u64().unwrap_or(0)
+        }
+    "#);
+
+    let indexes: Vec<_> = findings.iter().filter(|v| v.panic_class == PanicClass::ImplicitPanic).collect();
+    assert_eq!(indexes.iter().filter(|v| v.pattern == VALUE_INDEX).count(), 2);
+
+    // A HashMap panics on a missing string key; that is no Value index
+    let map = indexes.iter().find(|v| v.code.contains("counts")).expect("map index reported");
+    assert_ne!(map.pattern, VALUE_INDEX);
+    assert_ne!(map.severity, Severity::Low);
+}
+
+#[test]
+fn string_key_index_on_unknown_receiver_is_not_a_value_index() {
+    let findings = scan(r#"
+        fn lookup(table: &Table) -> u64 {
+            table["x"]
+        }
+    "#);
+
+    let index = only(&findings, "Array/Slice Indexing");
+    assert_eq!(index.severity, Severity::Medium);
+}
+
+#[test]
+fn bare_value_type_needs_serde_json_import() {
+    let findings = scan(r#"
+        use toml::Value;
+
+        fn port(cfg: &Value, other: &my_crate::Value) -> i64 {
+            cfg["port"].as_integer().unwrap_or(0) + other["port"].as_integer().unwrap_or(0)
+        }
+    "#);
+    assert!(findings.iter().all(|v| v.pattern != VALUE_INDEX), "{:#?}", findings);
+
+    let findings = scan(r#"
+        use serde_json::{json, Value};
+
+        fn port(cfg: &Value) -> u64 {
+            cfg["port"].as_u64().unwrap_or(0)
+        }
+    "#);
+    only(&findings, VALUE_INDEX);
+}
+
+#[test]
+fn renamed_or_foreign_value_types_are_not_json() {
+    let findings = scan(r#"
+        use serde_json::Value as Json;
+
+        fn port(a: &Value, b: &serde_yaml::Value, c: &toml::Value) -> u64 {
+            let d: Value = load();
+            a["p"].as_u64().unwrap_or(0) + b["p"].as_u64().unwrap_or(0)
+                + c["p"].as_u64().unwrap_or(0) + d["p"].as_u64().unwrap_or(0)
+        }
+    "#);
+    assert!(findings.iter().all(|v| v.pattern != VALUE_INDEX), "{:#?}", findings);
+}
+
+#[test]
+fn unwrap_in_iterator_adapter_closure_is_annotated() {
+    let findings = scan(r#"
+        fn ids(items: &[String]) -> Vec<u32> {
+            items.iter().map(|s| s.parse::<u32>().unwrap()).collect()
+        }
+    "#);
+
+    let unwrap = only(&findings, ADAPTER_PATTERN);
+    assert_eq!(unwrap.iterator_adapter.as_deref(), Some("map"));
+    assert!(matches!(unwrap.severity, Severity::High | Severity::Medium));
+}
+
+#[test]
+fn option_map_closure_is_not_an_iterator_adapter() {
+    let findings = scan(r#"
+        fn first_id(item: Option<&str>) -> Option<u32> {
+            item.map(|s| s.parse::<u32>().unwrap())
+        }
+    "#);
+
+    assert_eq!(findings.len(), 1, "{:#?}", findings);
+    assert_eq!(findings[0].iterator_adapter, None);
+    assert_ne!(findings[0].pattern, ADAPTER_PATTERN);
+}
+
+#[test]
+fn nonzero_new_unwrap_is_medium_unless_the_literal_is_nonzero() {
+    let runtime = unwrap_finding("NonZeroU32::new(n).unwrap()");
+    assert_eq!(runtime.severity, Severity::Medium);
+    assert_eq!(runtime.pattern, "Unwrapped NonZero::new (zero value panic)");
+
+    let literal = unwrap_finding("NonZeroU32::new(1).unwrap()");
+    assert_eq!(literal.severity, Severity::Low);
+    assert_eq!(literal.pattern, "Unwrapped NonZero::new (zero value panic)");
+
+    let zero = unwrap_finding("NonZeroU32::new(0).unwrap()");
+    assert_eq!(zero.severity, Severity::Medium);
+}
+
+#[test]
+fn unwrapped_timeout_is_high() {
+    let findings = scan(r#"
+        async fn fetch(d: Duration, f: impl Future<Output = u8>) -> u8 {
+            timeout(d, f).await.unwrap()
+        }
+    "#);
+
+    let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.pattern, "Unwrapped timeout (elapsed → panic)");
+}
+
+#[test]
+fn unwrapped_async_channel_recv_is_panic_amplification() {
+    let findings = scan(r#"
+        async fn next(rx: &mut tokio::sync::mpsc::Receiver<u8>) -> u8 {
+            rx.recv().await.unwrap()
+        }
+    "#);
+
+    let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::PanicAmplification);
+    assert_eq!(unwrap.pattern, "Unwrapped async channel op");
+}
+
+#[test]
+fn unwrapped_async_channel_send_is_panic_amplification() {
+    let unwrap = unwrap_finding("async { tx.send(1).await.unwrap() }");
+    assert_eq!(unwrap.panic_class, PanicClass::PanicAmplification);
+    assert_eq!(unwrap.pattern, "Unwrapped async channel op");
+}
+
+#[test]
+fn unwrapped_subprocess_execution_is_high() {
+    let unwrap = unwrap_finding(r#"Command::new("ls").output().unwrap()"#);
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped subprocess execution");
+
+    let expect = unwrap_finding(r#"Command::new("sh").spawn().expect("spawn")"#);
+    assert_eq!(expect.pattern, "Unwrapped subprocess execution");
+}
+
+#[test]
+fn command_bindings_are_tracked() {
+    let findings = scan(r#"
+use std::process::Command;
+pub fn git() {
+    let mut cmd = Command::new("git");
+    cmd.arg("status");
+    cmd.output().unwrap();
+}
+pub fn run(cmd: &mut std::process::Command) {
+    cmd.status().unwrap();
+}
+"#);
+    let subprocess: Vec<_> = findings.iter().filter(|v| v.pattern == "Unwrapped subprocess execution").collect();
+    assert_eq!(subprocess.len(), 2, "{:#?}", findings);
+}
+
+#[test]
+fn status_and_spawn_without_a_command_are_not_subprocesses() {
+    for expr in [
+        "response.status().unwrap()",
+        "std::thread::Builder::new().spawn(|| {}).unwrap()",
+        r#"MyCommand::new("x").output().unwrap()"#,
+    ] {
+        assert_ne!(unwrap_finding(expr).pattern, "Unwrapped subprocess execution", "{}", expr);
+    }
+}
+
+#[test]
+fn unwrapped_base64_decode_is_high() {
+    let unwrap = unwrap_finding("base64::decode(&body).unwrap()");
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped base64/hex decode of untrusted input");
+
+    for expr in [r#"hex::decode(s).expect("hex")"#, "STANDARD.decode(input).unwrap()"] {
+        assert_eq!(unwrap_finding(expr).pattern, "Unwrapped base64/hex decode of untrusted input", "{}", expr);
+    }
+}
+
+#[test]
+fn unwrapped_fixed_array_conversion_is_critical() {
+    let unwrap = unwrap_finding("<[u8;4]>::try_from(&buf[..4]).unwrap()");
+    assert_eq!(unwrap.severity, Severity::Critical);
+    assert_eq!(unwrap.panic_class, PanicClass::CloudflareClass);
+    assert_eq!(unwrap.pattern, "Fixed-size array conversion from untrusted slice + unwrap");
+
+    let try_into = unwrap_finding(r#"u32::from_le_bytes(buf[0..4].try_into().expect("4 bytes"))"#);
+    assert_eq!(try_into.pattern, "Fixed-size array conversion from untrusted slice + unwrap");
+}
+
+#[test]
+fn unwrapped_weak_upgrade_is_medium() {
+    let findings = scan(r#"
+        impl Node {
+            fn parent(&self) -> Rc<Node> {
+                self.parent.upgrade().unwrap()
+            }
+        }
+    "#);
+
+    let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+    assert_eq!(unwrap.severity, Severity::Medium);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped Weak::upgrade (dangling reference panic)");
+}
+
+#[test]
+fn dynamic_regex_compiled_in_a_loop_is_high() {
+    let findings = scan(r#"
+        fn matching(patterns: &[String], line: &str) -> usize {
+            let mut hits = 0;
+            for p in patterns {
+                if Regex::new(p).unwrap().is_match(line) {
+                    hits += 1;
+                }
+            }
+            hits
+        }
+    "#);
+
+    let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.pattern, "Dynamic pattern compiled per iteration (crash + perf)");
+    assert!(unwrap.hot_path);
+}
+
+#[test]
+fn regex_compiled_in_a_lazy_init_is_low() {
+    let findings = scan(r#"
+        static WORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"\w+").unwrap());
+    "#);
+
+    let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+    assert_eq!(unwrap.severity, Severity::Low);
+    assert_eq!(unwrap.pattern, "Unwrapped pattern compile in one-time init");
+}
+
+#[test]
+fn unwrapped_dns_resolution_is_high() {
+    let unwrap = unwrap_finding("addr.to_socket_addrs().unwrap()");
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped DNS resolution");
+
+    // The trailing `.next().unwrap()` is part of the same resolution
+    let findings = scan("pub fn f(host: &str) { let _ = host.to_socket_addrs().unwrap().next().unwrap(); }");
+    let unwraps: Vec<_> = findings.iter().filter(|v| v.rule_id == rules::RULE_UNWRAP.id).collect();
+    assert!(!unwraps.is_empty());
+    assert!(unwraps.iter().all(|v| v.pattern == "Unwrapped DNS resolution"), "{:#?}", unwraps);
+}
+
+#[test]
+fn unwraps_in_a_trusted_fn_are_downgraded() {
+    let config = Config { trusted_functions: vec!["build_*".to_string()], ..Config::default() };
+    let findings = scan_with(config, r#"
+        fn build_default() -> String {
+            std::fs::read_to_string("defaults.txt").unwrap()
+        }
+        fn load() -> String {
+            std::fs::read_to_string("data.txt").unwrap()
+        }
+    "#);
+
+    let unwraps: Vec<_> = findings.iter().filter(|v| v.rule_id == rules::RULE_UNWRAP.id).collect();
+    assert_eq!(unwraps.len(), 2, "{:#?}", findings);
+    let (trusted, untrusted) = (unwraps[0], unwraps[1]);
+    assert_eq!(trusted.severity, Severity::Low);
+    assert!(trusted.pattern.ends_with("(in trusted fn `build_default`)"), "{}", trusted.pattern);
+    assert_eq!(untrusted.severity, Severity::Critical);
+    assert!(!untrusted.pattern.contains("trusted fn"));
+}
+
+#[test]
+fn unwrapped_option_combinator_chain_is_medium() {
+    for expr in ["a.zip(b).unwrap()", "a.zip(b).map(|(x, y)| x + y).unwrap()", "a.and_then(parse).filter(is_valid).unwrap()"] {
+        let unwrap = unwrap_finding(expr);
+        assert_eq!(unwrap.severity, Severity::Medium, "{}", expr);
+        assert_eq!(unwrap.pattern, "Unwrapped option combinator chain", "{}", expr);
+    }
+}
+
+#[test]
+fn map_index_is_high() {
+    let findings = scan(r#"
+        fn port(config_map: &HashMap<String, String>) -> &str {
+            &config_map["missing"]
+        }
+    "#);
+
+    let index = only(&findings, rules::RULE_INDEXING.id);
+    assert_eq!(index.severity, Severity::High);
+    assert_eq!(index.pattern, "Map index [] (missing key panic)");
+}
+
+#[test]
+fn map_index_uses_whole_words_of_the_name() {
+    let findings = scan(r#"
+        fn read(mmap: &[u8], heatmap: &[u32], roadmap: &[String], bitmap: &[u64], i: usize) {
+            let _ = (mmap[i], heatmap[i], &roadmap[i], bitmap[i]);
+        }
+        fn lookup(id_map: &Ids, by_name_hashmap: &Names, counts: BTreeMap<u32, u32>, k: u32) {
+            let _ = (id_map[&k], by_name_hashmap[&k], counts[&k]);
+        }
+    "#);
+
+    let indexes: Vec<(&str, &str)> = findings
+        .iter()
+        .filter(|v| v.rule_id == rules::RULE_INDEXING.id)
+        .map(|v| (v.code.as_str(), v.pattern.as_str()))
+        .collect();
+    assert_eq!(indexes.len(), 7, "{:#?}", indexes);
+    for (code, pattern) in indexes {
+        let is_map = ["id_map", "by_name_hashmap", "counts"].iter().any(|name| code.starts_with(name));
+        assert_eq!(pattern == "Map index [] (missing key panic)", is_map, "{}: {}", code, pattern);
+    }
+}
+
+#[test]
+fn unwrapped_directory_listing_is_high() {
+    let unwrap = unwrap_finding("fs::read_dir(dir).unwrap()");
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped filesystem traversal/metadata");
+
+    for expr in ["path.metadata().unwrap()", "fs::symlink_metadata(p).unwrap()", "p.canonicalize().unwrap()"] {
+        assert_eq!(unwrap_finding(expr).pattern, "Unwrapped filesystem traversal/metadata", "{}", expr);
+    }
+}
+
+#[test]
+fn unwrapped_config_directory_listing_is_cloudflare_class() {
+    let unwrap = unwrap_finding(r#"fs::read_dir("config.d").unwrap()"#);
+    assert_eq!(unwrap.severity, Severity::Critical);
+    assert_eq!(unwrap.panic_class, PanicClass::CloudflareClass);
+}
+
+#[test]
+fn arc_get_mut_unwrap_is_flagged_but_try_unwrap_is_not() {
+    let unwrap = unwrap_finding("Arc::get_mut(&mut a).unwrap()");
+    assert_eq!(unwrap.severity, Severity::Medium);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped Arc/Rc::get_mut (shared reference panic)");
+    assert_eq!(unwrap_finding("Rc::get_mut(&mut r).expect(\"unique\")").pattern, unwrap.pattern);
+
+    let findings = scan("pub fn f(a: Arc<u8>) { let _ = Arc::try_unwrap(a).unwrap(); }");
+    assert!(findings.is_empty(), "{:#?}", findings);
+}
+
+#[test]
+fn allocation_sized_by_a_read_length_is_critical() {
+    let findings = scan(r#"
+        fn frame(buf: &[u8]) -> Vec<u8> {
+            let n = read_len(&buf);
+            let v = Vec::with_capacity(n);
+            v
+        }
+    "#);
+
+    let alloc = only(&findings, rules::RULE_UNTRUSTED_ALLOC.id);
+    assert_eq!(alloc.severity, Severity::Critical);
+    assert_eq!(alloc.panic_class, PanicClass::CloudflareClass);
+    assert_eq!(alloc.pattern, "Allocation sized by untrusted length field");
+}
+
+#[test]
+fn untrusted_lengths_come_from_buf_reads_not_any_getter() {
+    let findings = scan(r#"
+        fn frame(buf: &mut Bytes, items: &Items) -> (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>) {
+            let len = buf.get_u32_le() as usize;
+            let count = items.get_item_count();
+            let index = items.get_index();
+            let big = buf.get_u16_be() as usize;
+            (vec![0; len], Vec::with_capacity(count), Vec::with_capacity(index), vec![0; big])
+        }
+    "#);
+
+    let allocs: Vec<&str> = findings
+        .iter()
+        .filter(|v| v.rule_id == rules::RULE_UNTRUSTED_ALLOC.id)
+        .map(|v| v.code.as_str())
+        .collect();
+    assert_eq!(allocs.len(), 2, "{:#?}", allocs);
+    assert!(allocs.iter().all(|code| code.contains("len") || code.contains("big")), "{:#?}", allocs);
+}
+
+#[test]
+fn unwrapped_current_dir_is_medium() {
+    let unwrap = unwrap_finding("env::current_dir().unwrap()");
+    assert_eq!(unwrap.severity, Severity::Medium);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped current_dir/current_exe (environment assumption)");
+
+    let exe = unwrap_finding(r#"std::env::current_exe().expect("exe path")"#);
+    assert_eq!(exe.pattern, "Unwrapped current_dir/current_exe (environment assumption)");
+}
+
+#[test]
+fn unwrapped_search_result_is_medium() {
+    let unwrap = unwrap_finding("items.iter().position(|x| *x == k).unwrap()");
+    assert_eq!(unwrap.severity, Severity::Medium);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped search result (no-match panic)");
+
+    for expr in ["items.iter().find(|x| x.id == id).unwrap()", "items.iter().rposition(|x| *x == 0).expect(\"zero\")"] {
+        assert_eq!(unwrap_finding(expr).pattern, "Unwrapped search result (no-match panic)", "{}", expr);
+    }
+}
+
+#[test]
+fn ignoring_lock_poisoning_is_medium() {
+    let findings = scan(r#"
+        fn bump(counter: &Mutex<u64>) {
+            let mut n = counter.lock().unwrap_or_else(|p| p.into_inner());
+            *n += 1;
+        }
+    "#);
+
+    let ignored = only(&findings, rules::RULE_POISON_IGNORED.id);
+    assert_eq!(ignored.severity, Severity::Medium);
+    assert_eq!(ignored.pattern, "Ignoring lock poisoning (operates on possibly-corrupt state)");
+    // Recovering is not an unwrap: no amplification finding alongside it
+    assert!(findings.iter().all(|v| v.rule_id != rules::RULE_UNWRAP.id), "{:#?}", findings);
+
+    let handled = scan("fn f(m: &RwLock<u8>) { let _ = m.read().unwrap_or_else(|_| panic!()); }");
+    assert!(handled.iter().all(|v| v.rule_id != rules::RULE_POISON_IGNORED.id), "{:#?}", handled);
+}
+
+#[test]
+fn unwrap_in_a_reducer_pipeline_keeps_the_adapter_context() {
+    let findings = scan(r#"
+        fn total(lines: &[&str]) -> u64 {
+            lines.iter().map(|s| s.parse::<u64>().unwrap()).sum::<u64>()
+        }
+    "#);
+
+    let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+    assert_eq!(unwrap.iterator_adapter.as_deref(), Some("map"));
+    assert_eq!(unwrap.pattern, ADAPTER_PATTERN);
+    assert!(unwrap.hot_path);
+
+    for reducer in ["fold(0, |acc, s| acc + s.parse::<u64>().unwrap())", "reduce(|a, b| a.checked_add(b).unwrap())"] {
+        let findings = scan(&format!("fn f(v: &[u64]) {{ let _ = v.iter().copied().{}; }}", reducer));
+        let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+        assert!(unwrap.iterator_adapter.is_some(), "{}: {:#?}", reducer, unwrap);
+    }
+}
+
+#[test]
+fn config_lookup_is_classified_once_per_chain() {
+    let findings = scan(r#"
+        fn port(config: &toml::Table) -> u64 {
+            config.get("port").unwrap().as_u64().unwrap()
+        }
+    "#);
+
+    let unwraps: Vec<_> = findings.iter().filter(|v| v.rule_id == rules::RULE_UNWRAP.id).collect();
+    assert_eq!(unwraps.len(), 2, "{:#?}", findings);
+    let lookups: Vec<_> = unwraps
+        .iter()
+        .filter(|v| v.pattern == "Unwrapped config key lookup (Cloudflare Pattern)")
+        .collect();
+    assert_eq!(lookups.len(), 1, "{:#?}", unwraps);
+    assert_eq!(lookups[0].severity, Severity::Critical);
+    assert_eq!(lookups[0].panic_class, PanicClass::CloudflareClass);
+    assert_eq!(rules::normalize_code(&lookups[0].code), "config.get(\"port\").unwrap()");
+}
+
+#[test]
+fn config_value_index_accessor_is_a_config_lookup() {
+    let unwrap = unwrap_finding(r#"settings["database"]["url"].as_str().unwrap()"#);
+    assert_eq!(unwrap.severity, Severity::Critical);
+    assert_eq!(unwrap.pattern, "Unwrapped config key lookup (Cloudflare Pattern)");
+}
+
+#[test]
+fn unwrapped_decompression_is_high() {
+    let unwrap = unwrap_finding("GzDecoder::new(bytes).read_to_end(&mut out).unwrap()");
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::AllocationPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped decompression of untrusted input (DoS/panic)");
+
+    let zstd = unwrap_finding("zstd::decode_all(&body[..]).expect(\"zstd\")");
+    assert_eq!(zstd.pattern, "Unwrapped decompression of untrusted input (DoS/panic)");
+}
+
+#[test]
+fn unwrapped_layout_construction_is_medium() {
+    let unwrap = unwrap_finding("Layout::from_size_align(n, a).unwrap()");
+    assert_eq!(unwrap.severity, Severity::Medium);
+    assert_eq!(unwrap.panic_class, PanicClass::AllocationPanic);
+    assert_eq!(unwrap.pattern, "Unwrapped Layout construction (invalid size/align panic)");
+
+    let array = unwrap_finding("std::alloc::Layout::array::<u64>(len).expect(\"layout\")");
+    assert_eq!(array.pattern, "Unwrapped Layout construction (invalid size/align panic)");
+}
+
+#[test]
+fn unwrap_in_runtime_main_is_capped_at_medium() {
+    let findings = scan(
+        r#"
+#[tokio::main]
+async fn main() {
+    let config = std::fs::read_to_string("config.toml").unwrap();
+}
+
+pub fn load() -> String {
+    std::fs::read_to_string("config.toml").unwrap()
+}
+"#,
+    );
+    let (entry, library): (Vec<_>, Vec<_>) =
+        findings.iter().partition(|v| v.span.is_some_and(|span| span.start.line == 4));
+    assert_eq!(entry.len(), 1, "{:#?}", findings);
+    assert!(entry[0].in_runtime_main);
+    assert_eq!(entry[0].severity, Severity::Medium);
+
+    assert_eq!(library.len(), 1, "{:#?}", findings);
+    assert!(!library[0].in_runtime_main);
+    assert_eq!(library[0].severity, Severity::Critical);
+}
+
+#[test]
+fn only_known_runtimes_mark_runtime_main() {
+    let findings = scan("#[my_macros::main]\nasync fn main() { std::fs::read_to_string(\"a\").unwrap(); }\n");
+    assert!(!findings.is_empty() && findings.iter().all(|v| !v.in_runtime_main), "{:#?}", findings);
+
+    let actix = scan("#[actix_web::main]\nasync fn main() { std::fs::read_to_string(\"a\").unwrap(); }\n");
+    assert!(!actix.is_empty() && actix.iter().all(|v| v.in_runtime_main), "{:#?}", actix);
+}
+
+#[test]
+fn unwrapped_stdin_read_and_its_parse_are_medium() {
+    let findings = scan(
+        r#"
+pub fn ask() -> u32 {
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf).unwrap();
+    let other = String::from("7");
+    let _ = other.trim().parse::<u32>().unwrap();
+    buf.trim().parse().unwrap()
+}
+"#,
+    );
+    let stdin: Vec<_> = findings.iter().filter(|v| v.pattern == rules::PATTERN_STDIN).collect();
+    assert_eq!(stdin.iter().map(|v| v.line).collect::<Vec<_>>(), [4, 7], "{:#?}", findings);
+    for vuln in stdin {
+        assert_eq!(vuln.severity, Severity::Medium);
+        assert_eq!(vuln.panic_class, PanicClass::AssumptionPanic);
+    }
+}
+
+#[test]
+fn unwrapped_lock_into_inner_is_medium_panic_amplification() {
+    let findings = scan("pub fn teardown(m: Arc<Mutex<State>>) -> State { Arc::try_unwrap(m).ok().unwrap().into_inner().unwrap() }\n");
+    let unwraps: Vec<_> = findings.iter().filter(|v| v.rule_id == rules::RULE_UNWRAP.id).collect();
+    assert_eq!(unwraps.len(), 1, "{:#?}", findings);
+    assert_eq!(unwraps[0].severity, Severity::Medium);
+    assert_eq!(unwraps[0].panic_class, PanicClass::PanicAmplification);
+    assert_eq!(unwraps[0].pattern, "Unwrapped lock into_inner (poison panic on teardown)");
+
+    let mutex = unwrap_finding("mutex.into_inner().unwrap()");
+    assert_eq!(mutex.pattern, "Unwrapped lock into_inner (poison panic on teardown)");
+
+    let writer = unwrap_finding("writer.into_inner().unwrap()");
+    assert_ne!(writer.pattern, "Unwrapped lock into_inner (poison panic on teardown)");
+}
+
+#[test]
+fn hot_path_attribute_escalates_findings_in_the_fn() {
+    let source = |attr: &str| format!("{}\npub fn pick(v: Option<u8>) -> u8 {{ v.unwrap() }}\n", attr);
+
+    let plain = unwrap_finding("v.unwrap()");
+    assert_eq!(plain.severity, Severity::Low);
+    assert!(!plain.hot_path);
+
+    for attr in ["#[panic_audit::hot_path]", "#[cfg_attr(panic_audit, panic_audit::hot_path)]"] {
+        let findings = scan(&source(attr));
+        let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+        assert!(unwrap.hot_path, "{}", attr);
+        assert_eq!(unwrap.severity, Severity::Medium, "{}", attr);
+    }
+
+    // Annotation and loop heuristic together still make one hot finding
+    let both = scan(&source("#[panic_audit::hot_path]").replace("v.unwrap()", "loop { return v.unwrap(); }"));
+    assert!(only(&both, rules::RULE_UNWRAP.id).hot_path);
+}
+
+#[test]
+fn unwrap_in_a_plain_loop_is_escalated() {
+    let findings = scan(r#"
+        pub fn total(items: &[Option<u8>]) -> u32 {
+            let mut sum = 0;
+            for item in items {
+                sum += item.unwrap() as u32;
+            }
+            sum
+        }
+    "#);
+
+    let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+    assert!(unwrap.hot_path);
+    assert_eq!(unwrap.severity, Severity::Medium);
+}
+
+#[test]
+fn unchecked_buf_read_is_critical_without_an_unwrap() {
+    let findings = scan(
+        r#"
+pub fn frame_len(buf: &mut impl Buf) -> u32 {
+    buf.advance(2);
+    buf.get_u32()
+}
+"#,
+    );
+    let reads: Vec<_> = findings.iter().filter(|v| v.rule_id == rules::RULE_BUF_READ.id).collect();
+    assert_eq!(reads.iter().map(|v| v.line).collect::<Vec<_>>(), [3, 4], "{:#?}", findings);
+    for read in reads {
+        assert_eq!(read.severity, Severity::Critical);
+        assert_eq!(read.panic_class, PanicClass::CloudflareClass);
+        assert_eq!(read.pattern, "Unchecked bytes::Buf read (insufficient bytes panic)");
+    }
+
+    // Other getters, and Buf-named methods on receivers that aren't buffers
+    let other = scan("pub fn f(buf: &Config, list: &mut List) { buf.get_name(); list.advance(1); }\n");
+    assert!(other.iter().all(|v| v.rule_id != rules::RULE_BUF_READ.id), "{:#?}", other);
+}
+
+#[test]
+fn buf_read_in_test_code_is_not_reported() {
+    let findings = scan("#[test]\nfn f() {\n    let mut buf = Bytes::from_static(b\"abcd\");\n    buf.get_u32();\n}\n");
+    assert!(findings.iter().all(|v| v.rule_id != rules::RULE_BUF_READ.id), "{:#?}", findings);
+}
+
+#[test]
+fn bounds_assumed_on_config_derived_data_is_critical() {
+    // The Cloudflare outage: a feature file grew past a preallocated limit
+    let findings = scan(
+        r#"
+pub fn apply(path: &Path) -> Result<()> {
+    let text = fs::read_to_string(path)?;
+    let cfg: FeatureFile = serde_json::from_str(&text)?;
+    let features = cfg.features;
+    let mut slots = [0u8; 200];
+    slots[features.len()] = 1;
+    let default = &features[cfg.default];
+    let first = features.first().unwrap();
+    let local = vec![1, 2, 3];
+    let _ = local[2];
+    Ok(())
+}
+"#,
+    );
+    let bounds: Vec<_> = findings.iter().filter(|v| v.pattern == CONFIG_BOUNDS_PATTERN).collect();
+    assert_eq!(bounds.iter().map(|v| v.line).collect::<Vec<_>>(), [7, 8, 9], "{:#?}", findings);
+    for vuln in bounds {
+        assert_eq!(vuln.severity, Severity::Critical);
+        assert_eq!(vuln.panic_class, PanicClass::CloudflareClass);
+    }
+}
+
+#[test]
+fn config_typed_parameters_are_config_data() {
+    let findings = scan("pub fn pick(settings: &Settings, i: usize) -> u8 { settings.weights[i] }\n");
+    assert_eq!(only(&findings, rules::RULE_INDEXING.id).pattern, CONFIG_BOUNDS_PATTERN);
+
+    let plain = scan("pub fn pick(weights: &[u8], i: usize) -> u8 { weights[i] }\n");
+    assert_ne!(only(&plain, rules::RULE_INDEXING.id).pattern, CONFIG_BOUNDS_PATTERN);
+}
+
+#[test]
+fn unwrapped_deserialize_over_unwrapped_open_is_one_finding() {
+    const PATTERN: &str = "Unwrapped deserialize of unwrapped File::open (missing file + malformed content)";
+    for expr in [
+        "serde_json::from_reader(File::open(p).unwrap()).unwrap()",
+        "serde_json::from_reader(BufReader::new(File::open(p).unwrap())).expect(\"config\")",
+        "bincode::deserialize_from(&mut std::fs::File::open(p).expect(\"open\")).unwrap()",
+    ] {
+        let findings = scan(&format!("pub fn load(p: &Path) -> Config {{ {} }}\n", expr));
+        assert_eq!(findings.len(), 1, "{}: {:#?}", expr, findings);
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert_eq!(findings[0].panic_class, PanicClass::CloudflareClass);
+        assert_eq!(findings[0].pattern, PATTERN);
+    }
+
+    // Without the outer unwrap, the open unwrap is reported on its own
+    let findings = scan("pub fn load(p: &Path) -> Result<Config> { serde_json::from_reader(File::open(p).unwrap()) }\n");
+    assert_eq!(findings.len(), 1, "{:#?}", findings);
+    assert_ne!(findings[0].pattern, PATTERN);
+}
+
+#[test]
+fn unwrapped_cli_arg_accessor_is_medium() {
+    const PATTERN: &str = "Unwrapped CLI arg accessor (missing arg panic)";
+    let unwrap = unwrap_finding("matches.get_one::<String>(\"name\").unwrap()");
+    assert_eq!(unwrap.severity, Severity::Medium);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, PATTERN);
+
+    for expr in ["args.value_of(\"out\").expect(\"out\")", "m.get_many::<u32>(\"ids\").unwrap()", "m.values_of(\"x\").unwrap()"] {
+        assert_eq!(unwrap_finding(expr).pattern, PATTERN, "{}", expr);
+    }
+}
+
+#[test]
+fn unwrapped_sqlite_operations_are_high_in_files_using_rusqlite() {
+    let findings = scan(
+        r#"
+use rusqlite::Connection;
+
+pub fn names(conn: &Connection, sql: &str) -> Vec<String> {
+    let mut stmt = conn.prepare(sql).unwrap();
+    let rows = stmt.query_map([], |row| Ok(row.get::<_, String>(0).unwrap())).unwrap();
+    rows.map(|r| r.unwrap()).collect()
+}
+"#,
+    );
+    let sqlite: Vec<_> = findings.iter().filter(|v| v.pattern == rules::PATTERN_SQLITE).collect();
+    assert_eq!(sqlite.iter().map(|v| v.line).collect::<Vec<_>>(), [5, 6, 6], "{:#?}", findings);
+    for vuln in sqlite {
+        assert_eq!(vuln.severity, Severity::High);
+        assert_eq!(vuln.panic_class, PanicClass::AssumptionPanic);
+    }
+
+    // Without rusqlite in the file, `prepare` is just a method
+    let other = scan("pub fn f(b: &Builder) -> Plan { b.prepare(1).unwrap() }\n");
+    assert!(other.iter().all(|v| v.pattern != rules::PATTERN_SQLITE), "{:#?}", other);
+}
+
+#[test]
+fn unwrapped_url_parse_is_high() {
+    const PATTERN: &str = "Unwrapped URL/URI parse of untrusted input";
+    let unwrap = unwrap_finding("Url::parse(&input).unwrap()");
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, PATTERN);
+
+    for expr in ["url::Url::parse(link).expect(\"url\")", "http::Uri::from_str(&raw).unwrap()"] {
+        assert_eq!(unwrap_finding(expr).pattern, PATTERN, "{}", expr);
+    }
+}
+
+#[test]
+fn detection_kind_records_how_a_finding_was_matched() {
+    // The call itself panics
+    assert_eq!(unwrap_finding("v.unwrap()").detection_kind, DetectionKind::Literal);
+    assert_eq!(unwrap_finding("Layout::from_size_align(n, a).unwrap()").detection_kind, DetectionKind::Literal);
+
+    // Names: a file read of something called config
+    let config = unwrap_finding("fs::read_to_string(\"config.toml\").unwrap()");
+    assert_eq!(config.panic_class, PanicClass::CloudflareClass);
+    assert_eq!(config.detection_kind, DetectionKind::Heuristic);
+    let buf = scan("pub fn f(buf: &mut Bytes) -> u32 { buf.get_u32() }\n");
+    assert_eq!(only(&buf, rules::RULE_BUF_READ.id).detection_kind, DetectionKind::Heuristic);
+
+    // Tracked state: an index into data derived from a loaded config
+    let bounds = scan("pub fn f(settings: &Settings, i: usize) -> u8 { settings.weights[i] }\n");
+    assert_eq!(only(&bounds, rules::RULE_INDEXING.id).detection_kind, DetectionKind::Contextual);
+}
+
+#[test]
+fn detection_kind_is_in_the_json_report() {
+    let vuln = unwrap_finding("v.unwrap()");
+    let json = serde_json::to_value(&vuln).unwrap();
+    assert_eq!(json["detection_kind"], "Literal");
+}
+
+#[test]
+fn panics_in_iterator_next_are_high() {
+    let findings = scan(
+        r#"
+impl Iterator for Frames {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.data[self.pos];
+        self.pos += 1;
+        Some(self.peek().unwrap())
+    }
+}
+
+impl Frames {
+    fn next(&mut self) -> u8 {
+        self.data[self.pos]
+    }
+}
+"#,
+    );
+    let next: Vec<_> = findings.iter().filter(|v| v.pattern == ITERATOR_NEXT_PATTERN).collect();
+    assert_eq!(next.iter().map(|v| v.line).collect::<Vec<_>>(), [6, 8], "{:#?}", findings);
+    assert!(next.iter().all(|v| v.severity == Severity::High));
+
+    // An inherent `next` is not Iterator::next
+    let inherent = findings.iter().find(|v| v.span.is_some_and(|span| span.start.line == 14)).expect("index in inherent next");
+    assert_ne!(inherent.pattern, ITERATOR_NEXT_PATTERN);
+}
+
+#[test]
+fn a_line_lookup_miss_is_flagged_not_silently_line_one() {
+    let finding = |code: &str| {
+        Vulnerability::new(
+            "src/lib.rs".to_string(),
+            1,
+            rules::RULE_UNWRAP.id,
+            Severity::Low,
+            PanicClass::AssumptionPanic,
+            "General Unwrap".to_string(),
+            code.to_string(),
+        )
+    };
+    // No span, as for snippets reconstructed from generated tokens
+    let mut scanner = Scanner::new("fixture".to_string(), Config::default());
+    scanner.current_file = "src/lib.rs".to_string();
+    scanner.current_source = "pub fn f(v: Option<u8>) -> u8 { v.unwrap() }\n".to_string();
+    scanner.push(finding("generated . unwrap ()"));
+    scanner.push(finding("v . unwrap ()"));
+
+    let [missing, found] = &scanner.vulnerabilities[..] else {
+        panic!("{:#?}", scanner.vulnerabilities);
+    };
+    assert!(missing.line_unresolved);
+    assert!(!found.line_unresolved);
+
+    // Parsed code has real spans and resolved lines
+    let parsed = scan("pub fn f(v: Option<u8>) -> u8 { v.unwrap() }\n");
+    assert!(parsed.iter().all(|v| !v.line_unresolved), "{:#?}", parsed);
+}
+
+#[test]
+fn unwrapped_radix_and_bignum_parse_is_high() {
+    const PATTERN: &str = "Unwrapped radix/number parse of untrusted input";
+    let unwrap = unwrap_finding("u64::from_str_radix(&hex, 16).unwrap()");
+    assert_eq!(unwrap.severity, Severity::High);
+    assert_eq!(unwrap.panic_class, PanicClass::AssumptionPanic);
+    assert_eq!(unwrap.pattern, PATTERN);
+
+    for expr in ["BigInt::from_str(s).unwrap()", "digits.parse::<BigUint>().expect(\"digits\")"] {
+        assert_eq!(unwrap_finding(expr).pattern, PATTERN, "{}", expr);
+    }
+    // A checked parse is not this pattern
+    assert_ne!(unwrap_finding("u64::from_str_radix(&hex, 16).ok().unwrap()").pattern, PATTERN);
+}
+
+#[test]
+fn lock_unwrap_then_lookup_unwrap_is_one_compound_finding() {
+    for expr in [
+        "m.lock().unwrap().get_mut(&k).unwrap()",
+        "cache.read().expect(\"poisoned\").get(&id).copied().unwrap()",
+    ] {
+        let findings = scan(&format!("pub fn f() {{ let _ = {}; }}\n", expr));
+        assert_eq!(findings.len(), 1, "{}: {:#?}", expr, findings);
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert_eq!(findings[0].panic_class, PanicClass::PanicAmplification);
+        assert_eq!(findings[0].pattern, COMPOUND_LOCK_PATTERN);
+    }
+
+    // A lock unwrap alone is not compound
+    let plain = scan("pub fn f() { let _ = m.lock().unwrap().len(); }\n");
+    assert!(plain.iter().all(|v| v.pattern != COMPOUND_LOCK_PATTERN), "{:#?}", plain);
+}
+
+#[test]
+fn size_argument_subtracting_runtime_values_is_high() {
+    let findings = scan(r#"
+        pub fn f(v: &mut Vec<u8>, total: usize, consumed: usize) {
+            v.resize(total - consumed, 0);
+            let _ = Vec::<u8>::with_capacity(total - consumed);
+        }
+    "#);
+
+    let sizes: Vec<_> = findings.iter().filter(|v| v.rule_id == rules::RULE_SIZE_UNDERFLOW.id).collect();
+    assert_eq!(sizes.len(), 2, "{:#?}", findings);
+    for size in sizes {
+        assert_eq!(size.severity, Severity::High);
+        assert_eq!(size.panic_class, PanicClass::AllocationPanic);
+        assert_eq!(size.pattern, "Allocation/size with possible usize underflow");
+    }
+
+    // A constant on either side cannot underflow at runtime
+    let constant = scan("pub fn f(v: &mut Vec<u8>, n: usize) { v.resize(n - 1, 0); v.truncate(MAX - HEADER); }\n");
+    assert!(constant.iter().all(|v| v.rule_id != rules::RULE_SIZE_UNDERFLOW.id), "{:#?}", constant);
+}
+
+#[test]
+fn chained_call_is_reported_on_its_own_line() {
+    let findings = scan("pub fn f(v: Option<u8>) -> u8 {\n    v\n        .map(|x| x + 1)\n        .unwrap()\n}\n");
+
+    let unwrap = only(&findings, rules::RULE_UNWRAP.id);
+    assert_eq!(unwrap.line, 4);
+    assert_eq!(unwrap.column, 10);
+    let span = unwrap.span.expect("span");
+    assert_eq!((span.start.line, span.start.column), (4, 10));
+    assert_eq!((span.end.line, span.end.column), (4, 18));
+}
+
+#[test]
+fn repeated_expression_text_gets_each_line() {
+    let findings = scan("pub fn f(a: Option<u8>) -> u8 {\n    let x = a.unwrap();\n    let y = a.unwrap();\n    x + y\n}\n");
+
+    let lines: Vec<_> = findings.iter().filter(|v| v.rule_id == rules::RULE_UNWRAP.id).map(|v| v.line).collect();
+    assert_eq!(lines, [2, 3]);
+}
+
+#[test]
+fn unwrapped_condvar_wait_is_medium_panic_amplification() {
+    for expr in [
+        "cvar.wait(guard).unwrap()",
+        "self.condvar.wait_timeout(guard, timeout).expect(\"poisoned\")",
+        "cvar.wait_while(guard, |pending| *pending).unwrap()",
+    ] {
+        let finding = unwrap_finding(expr);
+        assert_eq!(finding.severity, Severity::Medium, "{}", expr);
+        assert_eq!(finding.panic_class, PanicClass::PanicAmplification, "{}", expr);
+        assert_eq!(finding.pattern, "Unwrapped Condvar wait (poison panic)", "{}", expr);
+    }
+
+    // `wait` on something that isn't a condvar
+    assert_ne!(unwrap_finding("child.wait().unwrap()").pattern, "Unwrapped Condvar wait (poison panic)");
+}
+
+#[test]
+fn panic_macro_is_critical_outside_tests() {
+    let findings = scan(r#"
+        pub fn handle(kind: u8) -> u8 {
+            if kind > 3 {
+                panic!("unknown kind {}", kind);
+            }
+            kind
+        }
+
+        #[test]
+        fn rejects() {
+            panic!("expected");
+        }
+    "#);
+
+    let panic = only(&findings, rules::RULE_PANIC.id);
+    assert_eq!(panic.severity, Severity::Critical);
+    assert_eq!(panic.panic_class, PanicClass::ImplicitPanic);
+    assert_eq!(panic.line, 4);
+}
+
+#[test]
+fn unreachable_macro_is_medium_and_high_in_pub_fns() {
+    let findings = scan(r#"
+        fn private(tag: u8) -> u8 {
+            match tag { 0 => 1, _ => unreachable!() }
+        }
+
+        pub fn public(tag: u8) -> u8 {
+            match tag { 0 => 1, _ => unreachable!("tag {}", tag) }
+        }
+    "#);
+
+    let unreachable: Vec<_> = findings.iter().filter(|v| v.rule_id == rules::RULE_UNREACHABLE.id).collect();
+    assert_eq!(unreachable.len(), 2, "{:#?}", findings);
+    assert!(unreachable.iter().all(|v| v.panic_class == PanicClass::ImplicitPanic));
+    assert_eq!((unreachable[0].line, &unreachable[0].severity), (3, &Severity::Medium));
+    assert_eq!((unreachable[1].line, &unreachable[1].severity), (7, &Severity::High));
+}
+
+#[test]
+fn multibyte_code_is_truncated_by_char() {
+    let text = "日本語🦀".repeat(75);
+    let source = format!("pub fn f() -> u8 {{\n    let _ = \"é\"; parse(\"{}\").unwrap()\n}}\n", text);
+    let findings = scan(&source);
+
+    let unwrap = findings.iter().find(|v| v.code.contains("parse")).expect("unwrap reported");
+    assert_eq!(unwrap.code.chars().count(), SNIPPET_CHARS);
+    assert!(unwrap.code.starts_with("parse (\"日本語🦀"), "{}", unwrap.code);
+    // Columns count characters: `    let _ = "é"; parse(...)` puts `unwrap` after the string
+    assert_eq!(unwrap.line, 2);
+    assert_eq!(unwrap.column, "    let _ = \"é\"; parse(\"\").".chars().count() + text.chars().count() + 1);
+
+    // The text-search fallback cuts its 40-char key by char as well
+    let mut scanner = Scanner::new("fixture".to_string(), Config::default());
+    scanner.current_source = source.clone();
+    assert_eq!(scanner.locate_in_source(&format!("parse(\"{}\")", text)), Some(2));
+}
+
+#[test]
+fn exploitability_scores_the_same_unwrap_by_context() {
+    let config = Config {
+        exploitability: crate::config::Exploitability { enabled: true, ..Default::default() },
+        ..Config::default()
+    };
+    let findings = scan_with(config, r#"
+        pub fn plain(v: Option<u8>) -> u8 { v.unwrap() }
+        pub fn hot(vs: &[Option<u8>]) { for v in vs { v.unwrap(); } }
+        pub extern "C" fn ffi(v: Option<u8>) -> u8 { v.unwrap() }
+        pub extern "C" fn ffi_hot(vs: &[Option<u8>]) { for v in vs { v.unwrap(); } }
+    "#);
+
+    let severities: Vec<_> = findings
+        .iter()
+        .filter(|v| v.rule_id == rules::RULE_UNWRAP.id)
+        .map(|v| (v.line, v.severity.clone()))
+        .collect();
+    // base 1; hot path +2; FFI abort +3
+    assert_eq!(
+        severities,
+        [(2, Severity::Low), (3, Severity::Medium), (4, Severity::High), (5, Severity::Critical)]
+    );
+}
+
+#[test]
+fn unwrapped_untrusted_file_parse_is_high() {
+    for expr in [
+        "image::load_from_memory(&bytes).unwrap()",
+        "rdr.records().next().unwrap()",
+        "calamine::open_workbook_auto(path).expect(\"workbook\")",
+    ] {
+        let finding = unwrap_finding(expr);
+        assert_eq!(finding.severity, Severity::High, "{}", expr);
+        assert_eq!(finding.panic_class, PanicClass::CloudflareClass, "{}", expr);
+        assert_eq!(finding.pattern, rules::PATTERN_FILE_PARSE, "{}", expr);
+    }
+}
+
+#[test]
+fn untrusted_parsers_config_adds_parse_calls() {
+    let source = "pub fn f(line: &str) { let _ = mylib::parse_record(line).unwrap(); }\n";
+    let is_file_parse = |findings: Vec<Vulnerability>| findings.iter().any(|v| v.pattern == rules::PATTERN_FILE_PARSE);
+    assert!(!is_file_parse(scan(source)));
+
+    let config = Config { untrusted_parsers: vec!["mylib::parse_record".to_string()], ..Config::default() };
+    assert!(is_file_parse(scan_with(config, source)));
+}
+
+#[test]
+fn cfg_test_modules_are_test_code() {
+    let gated = |cfg: &str| {
+        let source = format!("#[cfg({})]\nmod helpers {{\n    pub fn fixture(v: Option<u8>) -> u8 {{ v.unwrap() }}\n}}\n", cfg);
+        scan(&source).is_empty()
+    };
+
+    assert!(gated("test"));
+    assert!(gated("all(test, unix)"));
+    assert!(gated("feature = \"test\""));
+    assert!(gated("feature = \"test-utils\""));
+    assert!(gated("feature = \"integration_test\""));
+
+    assert!(!gated("not(test)"));
+    assert!(!gated("feature = \"attestation\""));
+    assert!(!gated("feature = \"latest-api\""));
+    assert!(!gated("feature = \"testing\""));
+}
+
+#[test]
+fn panics_in_parallel_iterator_closures_are_high() {
+    let findings = scan(r#"
+        pub fn parse_all(items: &[String]) -> Vec<u32> {
+            items.par_iter().map(|s| s.parse::<u32>().unwrap()).collect()
+        }
+
+        pub fn bridged(rx: Receiver<Option<u8>>) -> Vec<u8> {
+            rx.into_iter().par_bridge().map(|v| v.unwrap()).collect()
+        }
+
+        pub fn sequential(items: &[Option<u8>]) -> Vec<u8> {
+            items.iter().map(|v| v.unwrap()).collect()
+        }
+    "#);
+
+    let parallel: Vec<_> = findings.iter().filter(|v| v.pattern == PARALLEL_ADAPTER_PATTERN).collect();
+    assert_eq!(parallel.iter().map(|v| v.line).collect::<Vec<_>>(), [3, 7], "{:#?}", findings);
+    assert!(parallel.iter().all(|v| v.severity == Severity::High));
+
+    let sequential = findings.iter().find(|v| v.line == 11).expect("sequential unwrap reported");
+    assert_ne!(sequential.pattern, PARALLEL_ADAPTER_PATTERN);
+}
+
+#[test]
+fn unwrapped_try_lock_is_medium_in_files_using_tokio_sync() {
+    let source = r#"
+        use tokio::sync::Mutex;
+
+        pub fn bump(m: &Mutex<u32>) {
+            *m.try_lock().unwrap() += 1;
+        }
+    "#;
+    let findings = scan(source);
+    let finding = only(&findings, rules::RULE_UNWRAP.id);
+    assert_eq!(finding.pattern, rules::PATTERN_ASYNC_TRY_LOCK);
+    assert_eq!(finding.severity, Severity::Medium);
+    assert_eq!(finding.panic_class, PanicClass::PanicAmplification);
+
+    // std's try_lock without tokio::sync in the file keeps its usual classification
+    let std_lock = scan("pub fn bump(m: &std::sync::Mutex<u32>) { *m.try_lock().unwrap() += 1; }\n");
+    assert!(std_lock.iter().all(|v| v.pattern != rules::PATTERN_ASYNC_TRY_LOCK), "{:#?}", std_lock);
+}
+
+#[test]
+fn index_right_after_unsafe_set_len_is_low_when_trusted() {
+    let source = r#"
+        pub fn fill(v: &mut Vec<u8>, n: usize, i: usize) -> u8 {
+            unsafe { v.set_len(n) };
+            let first = v[i];
+            let later = v[i + 1];
+            first + later
+        }
+    "#;
+    let indexes = |findings: Vec<Vulnerability>| -> Vec<(usize, Severity, String)> {
+        findings
+            .into_iter()
+            .filter(|v| v.rule_id == rules::RULE_INDEXING.id)
+            .map(|v| (v.line, v.severity, v.pattern))
+            .collect()
+    };
+
+    let default = indexes(scan(source));
+    assert!(default.iter().all(|(_, severity, _)| *severity != Severity::Low), "{:#?}", default);
+
+    let trusted = indexes(scan_with(Config { trust_unsafe_invariants: true, ..Config::default() }, source));
+    assert_eq!(trusted.len(), 2, "{:#?}", trusted);
+    // Only the statement right after the unsafe one
+    assert_eq!((trusted[0].0, &trusted[0].1), (4, &Severity::Low));
+    assert!(trusted[0].2.ends_with("(after unsafe invariant)"), "{}", trusted[0].2);
+    assert_ne!(trusted[1].1, Severity::Low);
+}
+
+#[test]
+fn unsafe_invariant_covers_only_the_next_statements_own_index_and_unwrap() {
+    let source = r#"
+        pub fn fill(v: &mut Vec<u8>, n: usize, i: usize) -> u8 {
+            unsafe { v.set_len(n) };
+            let first = v.get(i).copied().unwrap() + v[i];
+            unsafe { v.set_len(n) };
+            let nested = { v[i] };
+            unsafe { v.set_len(n) };
+            let lazy = |j: usize| v[j];
+            unsafe { v.set_len(n) };
+            for j in 0..n { let _ = v[j]; }
+            unsafe { v.set_len(n) };
+            if first == 0 { panic!("empty") }
+            first + nested + lazy(i)
+        }
+    "#;
+    let trusted = scan_with(Config { trust_unsafe_invariants: true, ..Config::default() }, source);
+    let low: Vec<(usize, &str)> = trusted
+        .iter()
+        .filter(|v| v.pattern.ends_with("(after unsafe invariant)"))
+        .map(|v| (v.line, v.rule_id.as_str()))
+        .collect();
+    assert_eq!(low, [(4, rules::RULE_UNWRAP.id), (4, rules::RULE_INDEXING.id)], "{:#?}", trusted);
+
+    // The nested block, the closure, the loop body and the panic! keep their severity
+    for line in [6, 8, 10, 12] {
+        assert!(trusted.iter().any(|v| v.line == line && v.severity != Severity::Low), "line {}: {:#?}", line, trusted);
+    }
+}
+
+#[test]
+fn panics_in_drop_are_critical_amplification() {
+    let findings = scan(r#"
+        impl Drop for Conn {
+            fn drop(&mut self) {
+                self.socket.take().unwrap();
+                let _ = self.buf[0];
+                panic!("dropped twice");
+            }
+        }
+
+        impl Conn {
+            pub fn drop(&mut self, v: Option<u8>) -> u8 { v.unwrap() }
+        }
+    "#);
+
+    let in_drop: Vec<_> = findings.iter().filter(|v| v.pattern == DROP_PATTERN).collect();
+    assert_eq!(in_drop.iter().map(|v| v.line).collect::<Vec<_>>(), [4, 5, 6], "{:#?}", findings);
+    assert!(in_drop
+        .iter()
+        .all(|v| v.severity == Severity::Critical && v.panic_class == PanicClass::PanicAmplification));
+
+    // An inherent method named `drop` is not Drop::drop
+    let inherent = findings.iter().find(|v| v.line == 11).expect("inherent unwrap reported");
+    assert_ne!(inherent.pattern, DROP_PATTERN);
+}
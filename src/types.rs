@@ -1,14 +1,26 @@
-use serde::Serialize;
+use crate::diff::BaselineDelta;
+use crate::fingerprint::finding_id;
+use crate::manifest::RunManifest;
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub enum Severity {
+    #[serde(alias = "critical")]
     Critical,  // Can cause cascading outages
+    #[serde(alias = "high")]
     High,      // Can crash request handlers
+    #[serde(alias = "medium")]
     Medium,    // Can fail under specific conditions
+    #[serde(alias = "low")]
     Low,       // Low-risk internal operations
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+impl Severity {
+    /// Most to least severe, for reports that list every level
+    pub const ALL: [Severity; 4] = [Severity::Critical, Severity::High, Severity::Medium, Severity::Low];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PanicClass {
     // Class 1: Assumption panics
     AssumptionPanic,
@@ -36,32 +48,254 @@ pub enum PanicClass {
     ProcessKilling,
 }
 
-#[derive(Debug, Serialize)]
+impl PanicClass {
+    pub const ALL: [PanicClass; 8] = [
+        PanicClass::AssumptionPanic,
+        PanicClass::ImplicitPanic,
+        PanicClass::PanicAmplification,
+        PanicClass::CloudflareClass,
+        PanicClass::AssertionFailure,
+        PanicClass::AllocationPanic,
+        PanicClass::FFIBoundary,
+        PanicClass::ProcessKilling,
+    ];
+}
+
+/// How a finding was matched, so triage can weigh name-based guesses
+/// differently from exact call shapes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DetectionKind {
+    /// The flagged code itself is the panic (`.unwrap()`, `panic!`, `v[i]`)
+    #[default]
+    Literal,
+    /// Classified by identifier names or call shapes (`config`, `query(`)
+    Heuristic,
+    /// Classified by surrounding state: tracked bindings, file imports,
+    /// enclosing loop or adapter
+    Contextual,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Vulnerability {
+    /// Content-addressed id from `fingerprint::finding_id`, stable across
+    /// line moves (empty in reports from before ids existed)
+    #[serde(default)]
+    pub id: String,
     pub file: String,
-    pub line: String,
+    #[serde(deserialize_with = "line_number")]
+    pub line: usize,
+    /// 1-based character column where the flagged expression starts
+    #[serde(default = "first_column")]
+    pub column: usize,
+    /// Rule id from `rules::all_rules()` (e.g. `PA001`)
+    pub rule_id: String,
     pub severity: Severity,
     pub panic_class: PanicClass,
     pub pattern: String,
     pub code: String,
+    /// Whether the classification came from the code alone or from names/context
+    #[serde(default)]
+    pub detection_kind: DetectionKind,
+    /// Iterator adapter (`map`, `for_each`, ...) whose closure contains this panic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterator_adapter: Option<String>,
+    /// Inside a loop body or per-element closure
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hot_path: bool,
+    /// Inside an `async fn` or `async` block
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_async: bool,
+    /// Inside an `extern "C"` function, where unwinding aborts the process
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_ffi: bool,
+    /// Inside a `#[tokio::main]`-style entry fn, where a panic aborts startup
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_runtime_main: bool,
+    /// In a `#[test]` fn or `tests/` file, scanned because of `--include-tests`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_test: bool,
+    /// Score under `--rule-severity-from-exploitability`, which set `severity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exploitability: Option<u32>,
+    /// Only found in `cargo expand` output; `line` refers to the expanded source
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub from_macro: bool,
+    /// The snippet wasn't found in the source, so `line` is a placeholder
+    /// (low confidence; see `--drop-unresolved`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub line_unresolved: bool,
+    /// Exact source range of the flagged expression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+    /// Other places the same rule fired on identical code (`--dedupe-across-files`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_at: Vec<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub file: String,
+    #[serde(deserialize_with = "line_number")]
+    pub line: usize,
+    #[serde(default = "first_column")]
+    pub column: usize,
+}
+
+/// Reports written before lines were numeric store them as strings
+fn line_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Line {
+        Number(usize),
+        Text(String),
+    }
+
+    match Line::deserialize(deserializer)? {
+        Line::Number(line) => Ok(line),
+        Line::Text(text) => text.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+fn first_column() -> usize {
+    1
+}
+
+/// Start and end of a finding in its file. Lines and columns are 1-based;
+/// columns count characters, and `end` points just past the last one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
 }
 
 impl Vulnerability {
     pub fn new(
         file: String,
-        line: String,
+        line: usize,
+        rule_id: &str,
         severity: Severity,
         panic_class: PanicClass,
         pattern: String,
         code: String,
     ) -> Self {
-        Self {
+        let mut vuln = Self {
+            id: String::new(),
             file,
             line,
+            column: 1,
+            rule_id: rule_id.to_string(),
             severity,
             panic_class,
             pattern,
             code,
+            detection_kind: DetectionKind::Literal,
+            iterator_adapter: None,
+            hot_path: false,
+            in_async: false,
+            in_ffi: false,
+            in_runtime_main: false,
+            in_test: false,
+            exploitability: None,
+            from_macro: false,
+            line_unresolved: false,
+            span: None,
+            also_at: Vec::new(),
+        };
+        vuln.id = finding_id(&vuln);
+        vuln
+    }
+}
+
+/// Everything a report renderer needs, independent of how it was scanned.
+/// `--json` writes it and `render` reads it back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub crate_name: String,
+    pub version: String,
+    /// Whether the configured gate failed, even if `--no-exit` masked it
+    pub would_fail: bool,
+    /// Parameters this report was produced with (empty for older reports)
+    #[serde(default)]
+    pub run: RunManifest,
+    pub findings: Vec<Vulnerability>,
+    /// Findings silenced by `// panic-audit:allow` comments, not in `findings`
+    #[serde(default)]
+    pub suppressed: usize,
+    /// The silenced findings themselves, so SARIF can show them as dismissed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suppressed_findings: Vec<Vulnerability>,
+    /// Trend against `--baseline`, when one was active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline_delta: Option<BaselineDelta>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The sample report, plus a finding with every optional field set
+    fn representative() -> Report {
+        let mut report = crate::formats::sample_report();
+        let everything = Vulnerability {
+            detection_kind: DetectionKind::Contextual,
+            iterator_adapter: Some("map".to_string()),
+            hot_path: true,
+            in_async: true,
+            in_ffi: true,
+            in_runtime_main: true,
+            in_test: true,
+            exploitability: Some(9),
+            from_macro: true,
+            line_unresolved: true,
+            also_at: vec![Location { file: "src/b.rs".to_string(), line: 3, column: 9 }],
+            span: Some(SourceSpan {
+                start: Position { line: 3, column: 9 },
+                end: Position { line: 3, column: 21 },
+            }),
+            ..report.findings[0].clone()
+        };
+        report.findings.push(everything);
+        report.suppressed = 1;
+        report.suppressed_findings = vec![report.findings[1].clone()];
+        report
+    }
+
+    #[test]
+    fn report_round_trips_through_json() {
+        let report = representative();
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<Report>(&json).unwrap(), report);
+
+        let findings = serde_json::to_string_pretty(&report.findings).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Vulnerability>>(&findings).unwrap(), report.findings);
+    }
+
+    #[test]
+    fn enums_serialize_as_their_variant_names() {
+        assert_eq!(serde_json::to_value(Severity::Critical).unwrap(), "Critical");
+        assert_eq!(serde_json::to_value(PanicClass::CloudflareClass).unwrap(), "CloudflareClass");
+        assert_eq!(serde_json::to_value(DetectionKind::Heuristic).unwrap(), "Heuristic");
+        for class in PanicClass::ALL {
+            let json = serde_json::to_string(&class).unwrap();
+            assert_eq!(serde_json::from_str::<PanicClass>(&json).unwrap(), class);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn older_findings_without_ids_columns_or_numeric_lines_still_load() {
+        let legacy = r#"{
+            "file": "src/lib.rs", "line": "12", "rule_id": "PA001", "severity": "High",
+            "panic_class": "AssumptionPanic", "pattern": "General Unwrap", "code": "x.unwrap()"
+        }"#;
+        let vuln: Vulnerability = serde_json::from_str(legacy).unwrap();
+        assert_eq!((vuln.line, vuln.column), (12, 1));
+        assert!(vuln.id.is_empty());
+        assert_eq!(vuln.detection_kind, DetectionKind::Literal);
+    }
+}
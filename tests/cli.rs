@@ -0,0 +1,729 @@
+//! End-to-end runs of the binary against small fixture crates
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// One Critical finding (unwrapped file I/O) in library code
+const CRITICAL: &str = r#"
+pub fn load() -> String {
+    std::fs::read_to_string("data.txt").unwrap()
+}
+"#;
+
+/// One Low, Medium, High and Critical finding, deliberately out of order
+const ONE_OF_EACH: &str = r#"
+pub fn low(v: Option<u8>) -> u8 { v.unwrap() }
+pub fn medium(v: &[u8], i: usize) -> u8 { v[i] }
+pub fn high(p: &str) -> u32 { p.parse::<u32>().expect("number") }
+pub fn critical() -> String { std::fs::read_to_string("data.txt").unwrap() }
+"#;
+
+/// A throwaway crate directory holding `files` (paths relative to its root)
+fn fixture(files: &[(&str, &str)]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().expect("temp dir");
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().expect("file in a directory")).expect("create fixture dir");
+        fs::write(path, content).expect("write fixture file");
+    }
+    dir
+}
+
+/// A run auditing a local path with extra flags, isolated from the CI
+/// environment it runs in
+fn command(dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cargo-panic-audit"));
+    command
+        .arg(dir)
+        .arg("--local")
+        .args(args)
+        .env("NO_COLOR", "1")
+        .env_remove("RUST_LOG")
+        .env_remove("GITHUB_STEP_SUMMARY");
+    command
+}
+
+fn audit(dir: &Path, args: &[&str]) -> Output {
+    command(dir, args).output().expect("run cargo-panic-audit")
+}
+
+fn json(output: &Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).expect("JSON report on stdout")
+}
+
+#[test]
+fn no_exit_exits_zero_even_when_the_gate_fails() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+
+    let gated = audit(dir.path(), &["--fail-on-findings"]);
+    assert_eq!(gated.status.code(), Some(1));
+
+    let output = audit(dir.path(), &["--fail-on-findings", "--no-exit", "--json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(json(&output)["would_fail"], true);
+}
+
+#[test]
+fn log_level_emits_a_finding_event() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+
+    let output = audit(dir.path(), &["--log-level", "info", "--json"]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    let event = log
+        .lines()
+        .find(|line| line.contains(" finding "))
+        .unwrap_or_else(|| panic!("no finding event in:\n{}", log));
+    assert!(event.contains("rule_id=PA001"), "{}", event);
+    assert!(event.contains("severity=Critical"), "{}", event);
+    assert!(event.contains("path=src/lib.rs"), "{}", event);
+
+    // Off by default
+    let quiet = audit(dir.path(), &["--json"]);
+    assert!(!String::from_utf8_lossy(&quiet.stderr).contains(" finding "));
+}
+
+/// Three Critical findings (unwrapped file I/O)
+const THREE_CRITICAL: &str = r#"
+pub fn load() -> (String, String, String) {
+    (
+        std::fs::read_to_string("a.txt").unwrap(),
+        std::fs::read_to_string("b.txt").unwrap(),
+        std::fs::read_to_string("c.txt").unwrap(),
+    )
+}
+"#;
+
+#[test]
+fn max_critical_budget_fails_when_exceeded() {
+    let dir = fixture(&[("src/lib.rs", THREE_CRITICAL)]);
+
+    let over = audit(dir.path(), &["--max-critical", "2", "--json"]);
+    assert_eq!(over.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&over.stderr);
+    assert!(stderr.contains("Critical budget exceeded: 3 found, max 2 (over by 1)"), "{}", stderr);
+
+    let within = audit(dir.path(), &["--max-critical", "3"]);
+    assert_eq!(within.status.code(), Some(0));
+}
+
+#[test]
+fn step_summary_is_appended_to_the_github_summary_file() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+    let summary = dir.path().join("step-summary.md");
+    fs::write(&summary, "# Earlier step\n").unwrap();
+
+    let output = command(dir.path(), &["--format", "github-step-summary"])
+        .env("GITHUB_STEP_SUMMARY", &summary)
+        .output()
+        .expect("run cargo-panic-audit");
+    assert!(output.stdout.is_empty(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let markdown = fs::read_to_string(&summary).unwrap();
+    assert!(markdown.starts_with("# Earlier step\n## Panic audit: "), "{}", markdown);
+    assert!(markdown.contains("| Critical | 1 |"));
+    assert!(markdown.contains("| **Total** | **1** |"));
+    assert!(markdown.contains("### What to fix first"));
+    assert!(markdown.contains("`src/lib.rs:3`"));
+}
+
+#[test]
+fn step_summary_falls_back_to_stdout() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+
+    let output = audit(dir.path(), &["--format", "github-step-summary"]);
+    let markdown = String::from_utf8_lossy(&output.stdout);
+    assert!(markdown.contains("| Critical | 1 |"), "{}", markdown);
+}
+
+#[test]
+fn run_manifest_reflects_the_chosen_flags() {
+    let dir = fixture(&[
+        ("src/lib.rs", CRITICAL),
+        ("panic-audit.toml", "trusted_functions = [\"build_*\"]\n\n[[policy]]\nfail_on = \"Critical\"\n"),
+    ]);
+    let config = dir.path().join("panic-audit.toml");
+
+    let output = audit(
+        dir.path(),
+        &["--json", "--no-exit", "--max-high", "3", "--config", config.to_str().unwrap()],
+    );
+    let run = &json(&output)["run"];
+
+    assert_eq!(run["tool_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(run["ruleset_hash"].as_str().map(str::len), Some(16));
+    assert_eq!(run["config_path"], config.to_str().unwrap());
+    assert_eq!(run["source"]["kind"], "local");
+    assert_eq!(run["source"]["id"], dir.path().to_str().unwrap());
+    assert!(run["started_at"].as_str().is_some_and(|at| at.ends_with('Z')));
+
+    let options = &run["options"];
+    assert_eq!(options["max_high"], 3);
+    assert_eq!(options["max_critical"], serde_json::Value::Null);
+    assert_eq!(options["no_exit"], true);
+    assert_eq!(options["fail_on_findings"], false);
+    assert_eq!(options["trusted_functions"], serde_json::json!(["build_*"]));
+    assert_eq!(options["policy_rules"], 1);
+}
+
+#[test]
+fn json_lines_diagnostics_carry_exact_spans() {
+    let lib = "pub fn load() -> String {\n    std::fs::read_to_string(\"data.txt\").unwrap()\n}\n\npub fn get(v: &[u8], i: usize) -> u8 {\n    v[i]\n}\n";
+    let dir = fixture(&[("src/lib.rs", lib)]);
+
+    let output = audit(dir.path(), &["--list-findings-json-lines"]);
+    let diagnostics: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("one JSON object per line"))
+        .collect();
+    assert_eq!(diagnostics.len(), 2, "{:#?}", diagnostics);
+
+    for diagnostic in &diagnostics {
+        let position = |end: &str| {
+            let at = &diagnostic[end];
+            (at["line"].as_u64().expect("line"), at["column"].as_u64().expect("column"))
+        };
+        let (start, end) = (position("start"), position("end"));
+        assert!(start.0 >= 1 && start.1 >= 1, "{}", diagnostic);
+        assert!(start < end, "{}", diagnostic);
+        assert_eq!(diagnostic["file"], "src/lib.rs");
+        assert_eq!(diagnostic["source"], "cargo-panic-audit");
+    }
+
+    // The `unwrap()` of the read, columns 41..49 of line 2, as an error
+    assert_eq!(diagnostics[0]["start"], serde_json::json!({ "line": 2, "column": 41 }));
+    assert_eq!(diagnostics[0]["end"], serde_json::json!({ "line": 2, "column": 49 }));
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["code"], "PA001");
+    // `v[i]`
+    assert_eq!(diagnostics[1]["start"], serde_json::json!({ "line": 6, "column": 5 }));
+    assert_eq!(diagnostics[1]["end"], serde_json::json!({ "line": 6, "column": 9 }));
+    assert_eq!(diagnostics[1]["code"], "PA006");
+}
+
+#[test]
+fn output_is_identical_across_runs_and_sorted() {
+    let files: Vec<(String, String)> = (0..40)
+        .map(|i| {
+            let source = format!(
+                "pub fn f{i}(v: &[u8], s: &str) -> u8 {{\n    let n: usize = s.parse().unwrap();\n    std::fs::read(\"f{i}\").unwrap();\n    v[n]\n}}\n"
+            );
+            (format!("src/m{}/mod{}.rs", i % 7, i), source)
+        })
+        .collect();
+    let files: Vec<(&str, &str)> = files.iter().map(|(path, source)| (path.as_str(), source.as_str())).collect();
+    let dir = fixture(&files);
+
+    let run = |format: &[&str]| {
+        let mut args = vec!["--no-exit"];
+        args.extend(format);
+        let output = audit(dir.path(), &args);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+
+    let human = || String::from_utf8(run(&["--verbose", "--summary"]).stdout).unwrap();
+    assert_eq!(human(), human());
+
+    let report = || {
+        let mut report = json(&run(&["--json"]));
+        report["run"]["started_at"] = serde_json::Value::Null;
+        report
+    };
+    let first = report();
+    assert_eq!(first, report());
+
+    let keys: Vec<(String, usize, String)> = first["findings"]
+        .as_array()
+        .expect("findings array")
+        .iter()
+        .map(|finding| {
+            let line = finding["line"].as_u64().unwrap_or(0) as usize;
+            (finding["file"].as_str().unwrap().to_string(), line, finding["rule_id"].as_str().unwrap().to_string())
+        })
+        .collect();
+    assert_eq!(keys.len(), 120);
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+}
+
+#[test]
+fn summary_lists_severities_most_severe_first() {
+    let dir = fixture(&[("src/lib.rs", ONE_OF_EACH)]);
+
+    for _ in 0..3 {
+        let output = audit(dir.path(), &["--summary", "--no-exit"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let severities: Vec<&str> = stdout
+            .lines()
+            .filter_map(|line| line.split_once(':').map(|(label, _)| label.trim()))
+            .filter(|label| ["CRITICAL", "HIGH", "MEDIUM", "LOW"].contains(label))
+            .collect();
+        assert_eq!(severities, ["CRITICAL", "HIGH", "MEDIUM", "LOW"], "{}", stdout);
+    }
+}
+
+#[test]
+fn render_prints_a_saved_report_like_the_scan_did() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+    let scanned = audit(dir.path(), &["--verbose", "--no-exit"]);
+    let saved = audit(dir.path(), &["--json", "--no-exit"]);
+    let path = dir.path().join("report.json");
+    fs::write(&path, &saved.stdout).unwrap();
+
+    let render = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_cargo-panic-audit"))
+            .arg("render")
+            .arg(&path)
+            .args(args)
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("run cargo-panic-audit render")
+    };
+
+    // Same report section as the scan that produced the JSON
+    let rendered = String::from_utf8(render(&["--verbose"]).stdout).unwrap();
+    let scanned = String::from_utf8(scanned.stdout).unwrap();
+    let report_section = |text: &str| text[text.find("AUDIT REPORT:").expect("report header")..].to_string();
+    assert_eq!(report_section(&rendered), report_section(&scanned));
+
+    // And back to JSON, unchanged
+    let json_again = render(&["--json"]);
+    assert_eq!(json(&json_again), json(&saved));
+}
+
+#[test]
+fn gate_flags_follow_their_documented_precedence() {
+    let each = fixture(&[("src/lib.rs", ONE_OF_EACH)]);
+    let low = fixture(&[("src/lib.rs", "pub fn low(v: Option<u8>) -> u8 { v.unwrap() }\n")]);
+
+    let cases: &[(&Path, &[&str], i32)] = &[
+        (each.path(), &[], 0),
+        (each.path(), &["--fail-on-findings"], 1),
+        (low.path(), &["--fail-on-findings"], 0),
+        (low.path(), &["--warnings-as-errors"], 1),
+        // Budgets replace --fail-on-findings and --warnings-as-errors
+        (each.path(), &["--max-critical", "1"], 0),
+        (each.path(), &["--max-critical", "0"], 1),
+        (each.path(), &["--fail-on-findings", "--max-critical", "1"], 0),
+        (low.path(), &["--warnings-as-errors", "--max-low", "1"], 0),
+        (low.path(), &["--warnings-as-errors", "--max-critical", "0"], 0),
+        (each.path(), &["--max-critical", "1", "--max-low", "0"], 1),
+        // --no-exit beats everything
+        (each.path(), &["--no-exit", "--fail-on-findings"], 0),
+        (each.path(), &["--no-exit", "--warnings-as-errors"], 0),
+        (each.path(), &["--no-exit", "--max-critical", "0"], 0),
+    ];
+    for (dir, args, expected) in cases {
+        let output = audit(dir, args);
+        assert_eq!(output.status.code(), Some(*expected), "{:?}", args);
+    }
+}
+
+#[test]
+fn dedupe_across_files_collapses_a_repeated_snippet() {
+    let dir = fixture(&[
+        ("src/a.rs", CRITICAL),
+        ("src/b.rs", CRITICAL),
+        ("src/c/mod.rs", CRITICAL),
+    ]);
+
+    let separate = json(&audit(dir.path(), &["--json"]));
+    assert_eq!(separate["findings"].as_array().unwrap().len(), 3);
+
+    let report = json(&audit(dir.path(), &["--json", "--dedupe-across-files"]));
+    let findings = report["findings"].as_array().unwrap();
+    assert_eq!(findings.len(), 1, "{:#}", report);
+    assert_eq!(findings[0]["file"], "src/a.rs");
+    let also_at: Vec<_> = findings[0]["also_at"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| (location["file"].as_str().unwrap(), location["line"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(also_at, [("src/b.rs", 3), ("src/c/mod.rs", 3)]);
+
+    let human = audit(dir.path(), &["--dedupe-across-files"]);
+    assert!(String::from_utf8_lossy(&human.stdout).contains("(3 occurrences)"));
+}
+
+#[test]
+fn exit_code_by_severity_encodes_the_worst_finding() {
+    let lines: Vec<&str> = ONE_OF_EACH.trim().lines().collect();
+    let cases = [
+        ("pub fn add(a: u8, b: u8) -> Option<u8> { a.checked_add(b) }\n".to_string(), 0),
+        (lines[0].to_string(), 13),
+        (lines[..2].join("\n"), 12),
+        (lines[..3].join("\n"), 11),
+        (ONE_OF_EACH.to_string(), 10),
+        // Order in the file doesn't matter, only the worst severity
+        (format!("{}\n{}", lines[3], lines[0]), 10),
+    ];
+    for (lib, expected) in &cases {
+        let dir = fixture(&[("src/lib.rs", lib)]);
+        let output = audit(dir.path(), &["--exit-code-by-severity"]);
+        assert_eq!(output.status.code(), Some(*expected), "{}", lib);
+    }
+
+    // It replaces the gate's exit code 1
+    let dir = fixture(&[("src/lib.rs", lines[0])]);
+    let output = audit(dir.path(), &["--exit-code-by-severity", "--warnings-as-errors"]);
+    assert_eq!(output.status.code(), Some(13));
+}
+
+#[test]
+fn pretty_context_caret_points_at_the_finding_column() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+    let finding = &json(&audit(dir.path(), &["--json"]))["findings"][0];
+    let column = finding["column"].as_u64().unwrap() as usize;
+
+    let output = audit(dir.path(), &["--pretty-context"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    let source = lines
+        .iter()
+        .position(|line| line.starts_with("> 3 | "))
+        .unwrap_or_else(|| panic!("no source line in\n{}", stdout));
+
+    assert_eq!(lines[source], "> 3 |     std::fs::read_to_string(\"data.txt\").unwrap()");
+    let caret = lines[source + 1];
+    assert_eq!(caret.trim_end().len() - 1, "> 3 | ".len() + column - 1, "{:?}", caret);
+    assert!(lines[source + 2].contains("PA001 Critical: File I/O Operation"), "{}", lines[source + 2]);
+}
+
+#[test]
+fn compare_rules_gates_on_findings_only_the_current_rules_produce() {
+    let dir = fixture(&[
+        ("src/lib.rs", ONE_OF_EACH),
+        ("candidate.toml", "[severity]\nPA006 = \"critical\"\n"),
+        ("in-use.toml", "[severity]\n"),
+    ]);
+    let path = |name: &str| dir.path().join(name).display().to_string();
+    let (candidate, in_use) = (path("candidate.toml"), path("in-use.toml"));
+
+    let output = audit(dir.path(), &["--json", "--config", &candidate, "--compare-rules", &in_use]);
+    let comparison = json(&output);
+    let side = |key: &str| -> Vec<(String, String)> {
+        comparison[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| (v["rule_id"].as_str().unwrap().to_string(), v["severity"].as_str().unwrap().to_string()))
+            .collect()
+    };
+    assert_eq!(side("only_current"), [("PA006".to_string(), "Critical".to_string())]);
+    assert_eq!(side("only_other"), [("PA006".to_string(), "Medium".to_string())]);
+    assert_eq!(comparison["unchanged"], 3);
+
+    // The candidate newly makes a finding Critical
+    let newly_critical = audit(dir.path(), &["--config", &candidate, "--compare-rules", &in_use, "--fail-on-findings"]);
+    assert_eq!(newly_critical.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&newly_critical.stderr).contains("gate failed"));
+
+    // The other way round only a Medium is new; the Critical both produce doesn't count
+    let reverse = audit(dir.path(), &["--config", &in_use, "--compare-rules", &candidate, "--fail-on-findings"]);
+    assert_eq!(reverse.status.code(), Some(0));
+    let by_severity = audit(dir.path(), &["--config", &in_use, "--compare-rules", &candidate, "--exit-code-by-severity"]);
+    assert_eq!(by_severity.status.code(), Some(12));
+}
+
+#[test]
+fn test_code_is_scanned_only_with_include_tests() {
+    let lib = r#"
+pub fn add(a: u8, b: u8) -> Option<u8> { a.checked_add(b) }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn adds() {
+        assert_eq!(super::add(1, 2).unwrap(), 3);
+    }
+}
+"#;
+    let integration = "#[test]\nfn loads() {\n    std::fs::read_to_string(\"fixture.toml\").unwrap();\n}\n";
+    let dir = fixture(&[("src/lib.rs", lib), ("tests/load.rs", integration)]);
+
+    let default = json(&audit(dir.path(), &["--json"]));
+    assert_eq!(default["findings"], serde_json::json!([]));
+
+    let included = json(&audit(dir.path(), &["--json", "--include-tests"]));
+    let findings = included["findings"].as_array().unwrap();
+    let mut files: Vec<_> = findings.iter().map(|v| v["file"].as_str().unwrap()).collect();
+    files.sort();
+    files.dedup();
+    assert_eq!(files, ["src/lib.rs", "tests/load.rs"], "{:#}", included);
+    assert!(findings.iter().all(|v| v["in_test"] == true), "{:#}", included);
+}
+
+#[test]
+fn crate_root_names_a_nested_local_scan() {
+    let dir = fixture(&[
+        ("Cargo.toml", "[package]\nname = \"real-name\"\nversion = \"0.1.0\"\n"),
+        ("src/inner/mod.rs", CRITICAL),
+    ]);
+    let nested = dir.path().join("src/inner");
+
+    let guessed = json(&audit(&nested, &["--json"]));
+    assert_eq!(guessed["crate_name"], "inner");
+
+    let root = dir.path().to_str().expect("utf-8 path");
+    let named = json(&audit(&nested, &["--json", "--crate-root", root]));
+    assert_eq!(named["crate_name"], "real-name");
+    assert_eq!(named["findings"].as_array().map(Vec::len), Some(1));
+}
+
+#[test]
+fn chained_unwrap_is_placed_on_its_own_line() {
+    let chained = "pub fn load() -> String {\n    std::fs::read_to_string(\"data.txt\")\n        .unwrap()\n}\n";
+    let dir = fixture(&[("src/lib.rs", chained)]);
+
+    let finding = &json(&audit(dir.path(), &["--json"]))["findings"][0];
+    assert_eq!((finding["line"].as_u64(), finding["column"].as_u64()), (Some(3), Some(10)));
+
+    let output = audit(dir.path(), &["--pretty-context"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    let source = lines
+        .iter()
+        .position(|line| line.starts_with("> 3 | "))
+        .unwrap_or_else(|| panic!("no source line in\n{}", stdout));
+    assert_eq!(lines[source], "> 3 |         .unwrap()");
+    assert_eq!(lines[source + 1].trim_end(), "    |          ^");
+
+    // An allow comment on the `.unwrap()` line covers it
+    let allowed = chained.replace(".unwrap()", ".unwrap() // panic-audit:allow PA001");
+    let dir = fixture(&[("src/lib.rs", &allowed)]);
+    let report = json(&audit(dir.path(), &["--json"]));
+    assert_eq!(report["findings"].as_array().map(Vec::len), Some(0), "{}", report);
+    assert_eq!(report["suppressed"], 1);
+}
+
+#[test]
+fn output_is_identical_for_any_thread_count() {
+    let files: Vec<(String, String)> = (0..40)
+        .map(|i| {
+            let source = format!(
+                "pub fn f{i}(v: &[u8], s: &str) -> u8 {{\n    let n: usize = s.parse().unwrap();\n    std::fs::read(\"f{i}\").unwrap();\n    v[n]\n}}\n"
+            );
+            (format!("src/m{}/mod{}.rs", i % 7, i), source)
+        })
+        .collect();
+    let files: Vec<(&str, &str)> = files.iter().map(|(path, source)| (path.as_str(), source.as_str())).collect();
+    let dir = fixture(&files);
+
+    let run = |threads: &str, format: &[&str]| {
+        let mut args = vec!["--threads", threads, "--no-exit"];
+        args.extend(format);
+        let output = audit(dir.path(), &args);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+
+    let human = |threads| String::from_utf8(run(threads, &["--verbose"]).stdout).unwrap();
+    assert_eq!(human("1"), human("8"));
+
+    let report = |threads| {
+        let mut report = json(&run(threads, &["--json"]));
+        report["run"]["started_at"] = serde_json::Value::Null;
+        report
+    };
+    let single = report("1");
+    assert_eq!(single["findings"].as_array().map(Vec::len), Some(120));
+    assert_eq!(single, report("8"));
+}
+
+#[test]
+fn zero_threads_is_rejected() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+    let output = audit(dir.path(), &["--threads", "0"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn sarif_keeps_allowed_findings_as_suppressed_results() {
+    let dir = fixture(&[(
+        "src/lib.rs",
+        "pub fn port(cfg: Option<u16>) -> u16 {\n    cfg.unwrap() // panic-audit:allow PA001\n}\n",
+    )]);
+
+    let output = audit(dir.path(), &["--sarif"]);
+    let sarif = json(&output);
+    let results = sarif["runs"][0]["results"].as_array().expect("results");
+    assert_eq!(results.len(), 1, "{:#?}", results);
+    assert_eq!(results[0]["ruleId"], "PA001");
+    assert_eq!(results[0]["suppressions"][0]["kind"], "inSource");
+}
+
+#[test]
+fn baseline_ratchet_fails_only_on_new_findings() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+    let path = dir.path().join("panic-baseline.json");
+    let baseline = path.to_str().unwrap();
+
+    // Accept today's findings: fingerprints, not lines
+    let written = audit(dir.path(), &["--baseline", baseline, "--write-baseline", "--fail-on-findings"]);
+    assert_eq!(written.status.code(), Some(0), "{}", String::from_utf8_lossy(&written.stderr));
+    let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let entries = saved["entries"].as_array().expect("entries");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["rule_id"], "PA001");
+    assert_eq!(entries[0]["file"], "src/lib.rs");
+    assert!(entries[0].get("line").is_none(), "{}", entries[0]);
+
+    let gated = || audit(dir.path(), &["--baseline", baseline, "--fail-on-findings"]);
+    assert_eq!(gated().status.code(), Some(0));
+
+    // Fixing the accepted finding leaves a stale entry, which doesn't fail
+    fs::write(dir.path().join("src/lib.rs"), "pub fn load() -> Option<String> { None }\n").unwrap();
+    let fixed = gated();
+    assert_eq!(fixed.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&fixed.stderr).contains("1 stale baseline entries"));
+
+    // A new Critical finding does
+    fs::write(dir.path().join("src/lib.rs"), "pub fn key() -> Vec<u8> { std::fs::read(\"key.bin\").unwrap() }\n").unwrap();
+    assert_eq!(gated().status.code(), Some(1));
+}
+
+#[test]
+fn baseline_reports_only_new_findings_with_a_delta() {
+    let dir = fixture(&[("src/lib.rs", CRITICAL)]);
+    let baseline = dir.path().join("panic-baseline.json");
+    let baseline = baseline.to_str().unwrap();
+
+    let written = audit(dir.path(), &["--baseline", baseline, "--write-baseline", "--json", "--no-exit"]);
+    assert_eq!(json(&written)["findings"].as_array().map(Vec::len), Some(0));
+
+    // An unrelated line above the accepted finding doesn't invalidate it
+    let lib = format!("use std::fs;\n{}\npub fn key() -> Vec<u8> {{ fs::read(\"key.bin\").unwrap() }}\n", CRITICAL);
+    fs::write(dir.path().join("src/lib.rs"), lib).unwrap();
+
+    let output = audit(dir.path(), &["--baseline", baseline, "--json"]);
+    let report = json(&output);
+    let findings = report["findings"].as_array().expect("findings");
+    assert_eq!(findings.len(), 1, "{:#?}", findings);
+    assert!(findings[0]["code"].as_str().unwrap().contains("key.bin"));
+    assert_eq!(
+        report["baseline_delta"],
+        serde_json::json!({ "introduced": 1, "introduced_critical": 1, "introduced_high": 0, "resolved": 0, "net": 1 })
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Introduced 1 new (1 Critical), resolved 0, net +1"), "{}", stderr);
+}
+
+#[test]
+fn effective_config_shows_flags_over_the_file() {
+    let dir = fixture(&[("panic-audit.toml", "max_depth = 3\ninclude_tests = false\ntrusted_functions = [\"checked\"]\n")]);
+    let config = dir.path().join("panic-audit.toml");
+    let effective = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_cargo-panic-audit"))
+            .arg("--config-print-effective")
+            .arg("--config")
+            .arg(&config)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+
+    let file_only = json(&effective(&["--json"]));
+    assert_eq!(file_only["max_depth"], 3);
+    assert_eq!(file_only["include_tests"], false);
+
+    let overridden = json(&effective(&["--json", "--max-depth", "7", "--include-tests"]));
+    assert_eq!(overridden["max_depth"], 7);
+    assert_eq!(overridden["include_tests"], true);
+    // Settings no flag touches keep the file's value
+    assert_eq!(overridden["trusted_functions"], serde_json::json!(["checked"]));
+
+    let toml = String::from_utf8_lossy(&effective(&["--max-depth", "7"]).stdout).into_owned();
+    assert!(toml.contains("command-line flags"), "{}", toml);
+    assert!(toml.lines().any(|line| line == "max_depth = 7"), "{}", toml);
+}
+
+#[test]
+fn summary_json_needs_deps() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-panic-audit"))
+        .args(["serde", "--summary-json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--deps"));
+}
+
+#[test]
+fn summary_json_prints_only_the_aggregate() {
+    // A path package only, so nothing is downloaded
+    let dir = fixture(&[("Cargo.lock", "version = 3\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\n")]);
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-panic-audit"))
+        .arg("--deps")
+        .arg(dir.path().join("Cargo.lock"))
+        .arg("--summary-json")
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let summary = json(&output);
+    assert_eq!(summary["skipped"], 1);
+    assert_eq!(summary["crates"], serde_json::json!([]));
+    assert!(summary.get("findings").is_none());
+}
+
+#[test]
+fn output_dir_writes_an_index_and_needs_a_file_format() {
+    // A path package only, so nothing is downloaded
+    let dir = fixture(&[("Cargo.lock", "version = 3\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\n")]);
+    let out = dir.path().join("reports");
+    let run = |format: &str| {
+        Command::new(env!("CARGO_BIN_EXE_cargo-panic-audit"))
+            .arg("--deps")
+            .arg(dir.path().join("Cargo.lock"))
+            .arg("--output-dir")
+            .arg(&out)
+            .args(["--format", format])
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap()
+    };
+
+    let human = run("human");
+    assert!(!human.status.success());
+    assert!(String::from_utf8_lossy(&human.stderr).contains("--output-dir needs a report format"));
+    assert!(!out.exists());
+
+    let output = run("json");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let index: serde_json::Value = serde_json::from_str(&fs::read_to_string(out.join("index.json")).unwrap()).unwrap();
+    assert_eq!(index["skipped"], 1);
+    assert_eq!(index["crates"], serde_json::json!([]));
+}
+
+#[test]
+fn strict_parse_exits_2_on_every_scan_path() {
+    let dir = fixture(&[
+        ("src/lib.rs", CRITICAL),
+        ("src/broken.rs", "pub fn broken( {\n"),
+        ("in-use.toml", "[severity]\n"),
+    ]);
+    let in_use = dir.path().join("in-use.toml").display().to_string();
+
+    let lenient = audit(dir.path(), &[]);
+    assert_eq!(lenient.status.code(), Some(0), "{}", String::from_utf8_lossy(&lenient.stderr));
+
+    let strict = audit(dir.path(), &["--strict-parse"]);
+    assert_eq!(strict.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&strict.stderr);
+    assert!(stderr.contains("strict parse failed: 1 file(s) not audited"), "{}", stderr);
+    assert!(stderr.contains("src/broken.rs: 1:"), "{}", stderr);
+
+    // Ahead of a failed gate, and on the --compare-rules path too
+    for args in [
+        &["--strict-parse", "--fail-on-findings"][..],
+        &["--strict-parse", "--compare-rules", &in_use][..],
+        &["--strict-parse", "--compare-rules", &in_use, "--exit-code-by-severity"][..],
+    ] {
+        assert_eq!(audit(dir.path(), args).status.code(), Some(2), "{:?}", args);
+    }
+}
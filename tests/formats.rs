@@ -0,0 +1,78 @@
+//! Snapshots of every report format, rendered from one saved report
+//!
+//! `render` reads `fixtures/report.json` (one finding per severity) so the
+//! output depends on nothing but the renderer. Review changes with
+//! `cargo insta review`.
+
+use std::path::Path;
+use std::process::Command;
+
+/// `render` of the fixture report with `args`, colors off
+fn render(args: &[&str]) -> String {
+    let report = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/report.json");
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-panic-audit"))
+        .arg("render")
+        .arg(report)
+        .args(["--color", "never"])
+        .args(args)
+        .env_remove("GITHUB_STEP_SUMMARY")
+        .output()
+        .expect("run cargo-panic-audit render");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).expect("UTF-8 output")
+}
+
+#[test]
+fn human_plain() {
+    insta::assert_snapshot!(render(&[]));
+}
+
+#[test]
+fn human_verbose() {
+    insta::assert_snapshot!(render(&["--verbose"]));
+}
+
+#[test]
+fn human_summary() {
+    insta::assert_snapshot!(render(&["--summary"]));
+}
+
+#[test]
+fn json() {
+    insta::assert_snapshot!(render(&["--format", "json"]));
+}
+
+#[test]
+fn junit() {
+    insta::assert_snapshot!(render(&["--format", "junit"]));
+}
+
+#[test]
+fn codeclimate() {
+    insta::assert_snapshot!(render(&["--format", "codeclimate"]));
+}
+
+#[test]
+fn sonarqube() {
+    insta::assert_snapshot!(render(&["--format", "sonarqube"]));
+}
+
+#[test]
+fn json_lines() {
+    insta::assert_snapshot!(render(&["--format", "json-lines"]));
+}
+
+#[test]
+fn github_step_summary() {
+    insta::assert_snapshot!(render(&["--format", "github-step-summary"]));
+}
+
+#[test]
+fn xml() {
+    insta::assert_snapshot!(render(&["--format", "xml"]));
+}
+
+#[test]
+fn sarif() {
+    insta::assert_snapshot!(render(&["--format", "sarif"]));
+}